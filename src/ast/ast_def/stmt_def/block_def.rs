@@ -1,5 +1,6 @@
 use crate::ast::ast_def::stmt_def::{Stat, Exp};
 
+#[derive(Debug)]
 pub struct Block {
     pub last_line: usize,
     pub stats: Vec<Box<dyn Stat>>,
@@ -1,63 +1,96 @@
+use std::fmt;
 use crate::ast::ast_def::stmt_def::Exp;
 use crate::ast::ast_def::stmt_def::block_def::Block;
+use crate::ast::lexer::token::KeyWord;
 
+#[derive(Debug)]
 pub struct NilExp {
     pub line: usize,
 }
 
+#[derive(Debug)]
 pub struct TrueExp {
     pub line: usize,
 }
 
+#[derive(Debug)]
 pub struct FalseExp {
     pub line: usize,
 }
 
+#[derive(Debug)]
 pub struct IntegerExp {
     pub line: usize,
     pub num: i64,
 }
 
+#[derive(Debug)]
 pub struct FloatExp {
     pub line: usize,
     pub num: f64,
 }
 
+#[derive(Debug)]
+pub struct VarargExp {
+    pub line: usize,
+}
+
+/// Lua strings are byte strings, so `str` may hold any bytes, e.g. from a
+/// `\xNN` escape.
 pub struct StringExp {
     pub line: usize,
-    pub str: String,
+    pub str: Vec<u8>,
 }
 
+impl fmt::Debug for StringExp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("StringExp");
+        debug.field("line", &self.line);
+        match std::str::from_utf8(&self.str) {
+            Ok(str) => debug.field("str", &str),
+            Err(_) => debug.field("str", &format_args!("b\"{}\"", self.str.escape_ascii())),
+        };
+        debug.finish()
+    }
+}
+
+#[derive(Debug)]
 pub struct IDExp {
     pub line: usize,
     pub name: String,
 }
 
+#[derive(Debug)]
 pub struct UnopExp {
     pub line: usize,
-    pub op: usize,
+    pub op: KeyWord,
     pub exp: Box<dyn Exp>,
 }
 
+#[derive(Debug)]
 pub struct BinopExp {
     pub line: usize,
-    pub op: usize,
+    pub op: KeyWord,
     pub left_exp: Box<dyn Exp>,
     pub right_exp: Box<dyn Exp>,
 }
 
+#[derive(Debug)]
 pub struct ConExp {
     pub line: usize,
     pub exps: Vec<Box<dyn Exp>>,
 }
 
+#[derive(Debug)]
 pub struct TableConsExp {
     pub line: usize,
     pub last_line: usize,
-    pub key_exps: Vec<Box<dyn Exp>>,
+    /// `None` for positional fields such as `{ 1, 2 }`.
+    pub key_exps: Vec<Option<Box<dyn Exp>>>,
     pub val_exps: Vec<Box<dyn Exp>>,
 }
 
+#[derive(Debug)]
 pub struct FuncDefExp {
     pub line: usize,
     pub last_line: usize,
@@ -66,20 +99,41 @@ pub struct FuncDefExp {
     pub block: Block,
 }
 
+#[derive(Debug)]
 pub struct ParensExp {
-    pub in_exp: Box<Box<dyn Exp>>
+    pub in_exp: Box<dyn Exp>,
 }
 
+#[derive(Debug)]
 pub struct TableAccessExp {
     pub last_line: usize,
     pub prefix: Box<dyn Exp>,
     pub key: Box<dyn Exp>,
 }
 
+#[derive(Debug)]
 pub struct FuncCallExp {
     pub line: usize,
     pub last_line: usize,
     pub prefix: Box<dyn Exp>,
-    pub name_exp: StringExp,
+    /// Method name of a `prefix:name(args)` call.
+    pub name_exp: Option<StringExp>,
     pub args: Vec<Box<dyn Exp>>,
-}
\ No newline at end of file
+}
+
+impl Exp for NilExp {}
+impl Exp for TrueExp {}
+impl Exp for FalseExp {}
+impl Exp for VarargExp {}
+impl Exp for IntegerExp {}
+impl Exp for FloatExp {}
+impl Exp for StringExp {}
+impl Exp for IDExp {}
+impl Exp for UnopExp {}
+impl Exp for BinopExp {}
+impl Exp for ConExp {}
+impl Exp for TableConsExp {}
+impl Exp for FuncDefExp {}
+impl Exp for ParensExp {}
+impl Exp for TableAccessExp {}
+impl Exp for FuncCallExp {}
\ No newline at end of file
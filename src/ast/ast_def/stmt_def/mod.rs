@@ -2,12 +2,27 @@ pub mod exp_def;
 pub mod stat_def;
 pub mod block_def;
 
-pub trait Exp {}
+use std::fmt::Debug;
+
+pub trait Exp: Debug {}
 
 pub enum StatType {
-    EmptyStatTag
+    EmptyStatTag,
+    BreakStatTag,
+    LabelStatTag,
+    GotoStatTag,
+    DoStatTag,
+    WhileStatTag,
+    RepeatStatTag,
+    IfStatTag,
+    FuncCallStatTag,
+    StepForStatTag,
+    RangeForStatTag,
+    LocalVarDefStatTag,
+    AssignStatTag,
+    LocalFuncDefStatTag,
 }
 
-pub trait Stat {
+pub trait Stat: Debug {
     fn get_type(&self) -> StatType;
-}
\ No newline at end of file
+}
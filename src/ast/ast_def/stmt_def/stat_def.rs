@@ -1,42 +1,53 @@
-use crate::ast::ast_def::stmt_def::Exp;
+use crate::ast::ast_def::stmt_def::{Exp, Stat, StatType};
 use crate::ast::ast_def::stmt_def::exp_def::{FuncDefExp, FuncCallExp};
 use crate::ast::ast_def::stmt_def::block_def::Block;
 
+#[derive(Debug)]
 pub struct EmptyStat {}
 
+#[derive(Debug)]
 pub struct BreakStat {
     pub line: usize,
 }
 
+#[derive(Debug)]
 pub struct LabelStat {
+    pub line: usize,
     pub name: String,
 }
 
+#[derive(Debug)]
 pub struct GotoStat {
+    pub line: usize,
     pub target: String,
 }
 
+#[derive(Debug)]
 pub struct DoStat {
     pub block: Block,
 }
 
+#[derive(Debug)]
 pub struct WhileStat {
     pub exp: Box<dyn Exp>,
     pub block: Block,
 }
 
+#[derive(Debug)]
 pub struct RepeatStat {
     pub block: Block,
     pub exp: Box<dyn Exp>,
 }
 
+#[derive(Debug)]
 pub struct IfStat {
     pub exps: Vec<Box<dyn Exp>>,
     pub blocks: Vec<Block>,
 }
 
-type FuncCallStat = FuncCallExp;
+pub type FuncCallStat = FuncCallExp;
 
+#[derive(Debug)]
 pub struct StepForStat {
     pub beg_line: usize,
     pub block_beg_line: usize,
@@ -47,6 +58,7 @@ pub struct StepForStat {
     pub block: Block,
 }
 
+#[derive(Debug)]
 pub struct RangeForStat {
     pub block_beg_line: usize,
     pub name_list: Vec<String>,
@@ -54,19 +66,49 @@ pub struct RangeForStat {
     pub block: Block,
 }
 
+#[derive(Debug)]
 pub struct LocalVarDefStat {
     pub last_line: usize,
     pub name_list: Vec<String>,
     pub exp_list: Vec<Box<dyn Exp>>,
 }
 
+#[derive(Debug)]
 pub struct AssignStat {
     pub last_line: usize,
     pub var_list: Vec<Box<dyn Exp>>,
     pub exp_list: Vec<Box<dyn Exp>>,
 }
 
+#[derive(Debug)]
 pub struct LocalFuncDefStat {
     pub name: String,
     pub exp: FuncDefExp,
 }
+
+macro_rules! impl_stat {
+    ($($STAT: ty => $TAG: ident), *) => {
+        $(impl Stat for $STAT {
+            fn get_type(&self) -> StatType {
+                StatType::$TAG
+            }
+        })*
+    }
+}
+
+impl_stat![
+    EmptyStat           =>  EmptyStatTag,
+    BreakStat           =>  BreakStatTag,
+    LabelStat           =>  LabelStatTag,
+    GotoStat            =>  GotoStatTag,
+    DoStat              =>  DoStatTag,
+    WhileStat           =>  WhileStatTag,
+    RepeatStat          =>  RepeatStatTag,
+    IfStat              =>  IfStatTag,
+    FuncCallStat        =>  FuncCallStatTag,
+    StepForStat         =>  StepForStatTag,
+    RangeForStat        =>  RangeForStatTag,
+    LocalVarDefStat     =>  LocalVarDefStatTag,
+    AssignStat          =>  AssignStatTag,
+    LocalFuncDefStat    =>  LocalFuncDefStatTag
+];
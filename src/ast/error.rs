@@ -0,0 +1,25 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result};
+
+/// An error found while lexing or parsing a chunk, reported as
+/// `chunk_name:line: msg` like the reference implementation does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub chunk_name: String,
+    pub line: usize,
+    pub msg: String,
+}
+
+impl SyntaxError {
+    pub fn new(chunk_name: &str, line: usize, msg: String) -> SyntaxError {
+        SyntaxError { chunk_name: chunk_name.to_string(), line, msg }
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{}:{}: {}", self.chunk_name, self.line, self.msg)
+    }
+}
+
+impl Error for SyntaxError {}
@@ -1,207 +1,520 @@
 pub mod token;
 pub mod util;
 
-use std::iter::{Peekable, FromIterator, IntoIterator};
 use std::collections::HashMap;
 use token::{get_key_word_map, get_opt_map, TokenType, Token};
+use crate::ast::error::SyntaxError;
 use crate::ast::lexer::token::KeyWord;
-use std::vec::IntoIter;
-use std::ops::Not;
+use crate::ast::lexer::util::{str_to_integer, str_to_float, utf8_escape};
+
+/// Errors are boxed so the results stay small on the parser's recursive
+/// paths.
+pub type LexResult<T> = Result<T, Box<SyntaxError>>;
 
 pub struct Lexer {
-    char_iter: Peekable<IntoIter<char>>,
+    chunk_name: String,
+    chars: Vec<char>,
+    pos: usize,
     opt_hash_map: HashMap<String, KeyWord>,
     key_word_hash_map: HashMap<String, KeyWord>,
     cur_line: usize,
     cur_column: usize,
     eof: bool,
-    cur_token: Option<Token>,
+    last_line: usize,
     next_token: Option<Token>,
+    ahead_token: Option<Token>,
 }
 
 impl Lexer {
-    pub fn new(source_code: &String) -> Lexer {
-        let chars = Vec::from_iter(source_code.chars());
+    pub fn new(source_code: &str, chunk_name: &str) -> Lexer {
         Lexer {
-            char_iter: chars.into_iter().peekable(),
+            chunk_name: chunk_name.to_string(),
+            chars: source_code.chars().collect(),
+            pos: 0,
             opt_hash_map: get_opt_map(),
             key_word_hash_map: get_key_word_map(),
             cur_line: 1,
             cur_column: 1,
             eof: false,
-            cur_token: None,
+            last_line: 1,
             next_token: None,
+            ahead_token: None,
         }
     }
 
-    fn iter_advance(&mut self, n: usize) {
-        self.cur_column += n;
+    fn error(&self, line: usize, msg: String) -> Box<SyntaxError> {
+        Box::new(SyntaxError::new(&self.chunk_name, line, msg))
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn peek_char_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).cloned()
+    }
+
+    /// Up to `len` characters starting at the current position.
+    fn peek_str(&self, len: usize) -> String {
+        let end = (self.pos + len).min(self.chars.len());
+        self.chars[self.pos..end].iter().collect()
     }
 
-    fn iter_new_line(&mut self) {
-        self.cur_line += 1;
-        self.cur_column = 1;
+    fn is_newline(ch: char) -> bool {
+        ch == '\n' || ch == '\r'
+    }
+
+    /// Takes one character. Like Lua, `\n`, `\r`, `\r\n` and `\n\r` each
+    /// count as a single newline and are returned as `\n`.
+    fn next_char(&mut self) -> Option<char> {
+        let ch = self.peek_char()?;
+        self.pos += 1;
+        if Lexer::is_newline(ch) {
+            if self.peek_char().is_some_and(|next| Lexer::is_newline(next) && next != ch) {
+                self.pos += 1;
+            }
+            self.cur_line += 1;
+            self.cur_column = 1;
+            return Some('\n');
+        }
+        self.cur_column += 1;
+        Some(ch)
+    }
+
+    fn skip_chars(&mut self, n: usize) {
+        for _ in 0..n {
+            self.next_char();
+        }
     }
 
     fn take_word<F>(&mut self, filter: F) -> String
         where F: Fn(&char) -> bool {
         let mut res: String = String::new();
-        while if let Some(ch) = self.char_iter.peek() {
-            filter(ch)
-        } else {
-            false
-        } {
-            res.push(self.char_iter.next().unwrap());
+        while let Some(ch) = self.peek_char().filter(&filter) {
+            res.push(ch);
+            self.next_char();
         }
         res
     }
 
+    fn is_white_space(ch: char) -> bool {
+        matches!(ch, ' ' | '\t' | '\r' | '\n' | '\u{0b}' | '\u{0c}')
+    }
+
     fn parse_id(&mut self) -> (TokenType, String) {
         let char_filter = |ch: &char| {
-            ch.is_alphanumeric() || *ch == '_'
+            ch.is_ascii_alphanumeric() || *ch == '_'
         };
-        //let id_str: String = self.char_iter.by_ref().take_while(char_filter).collect();
         let id_str = self.take_word(char_filter);
 
-        self.iter_advance(id_str.len());
         match self.key_word_hash_map.get(&id_str) {
-            Some(key_word) => (TokenType::OptKeyWord(key_word.clone()), id_str.clone()),
-            None => (TokenType::ID(id_str.clone()), id_str.clone())
+            Some(key_word) => (TokenType::OptKeyWord(key_word.clone()), id_str),
+            None => (TokenType::ID(id_str.clone()), id_str)
         }
     }
 
-    fn parse_number(&mut self) -> (TokenType, String) {
-        let num_filter = |ch: &char| {
-            ch.is_alphanumeric() || *ch == '.' || *ch == '_'
-        };
-        let num_str: String = self.take_word(num_filter);
+    fn parse_number(&mut self) -> LexResult<(TokenType, String)> {
+        let mut num_str = String::new();
+        let is_hex = self.peek_str(2).eq_ignore_ascii_case("0x");
+        let exp_chars = if is_hex { ['p', 'P'] } else { ['e', 'E'] };
+        while let Some(ch) = self.peek_char() {
+            if ch.is_ascii_alphanumeric() || ch == '.' || ch == '_'
+                || ((ch == '+' || ch == '-') && num_str.ends_with(&exp_chars[..])) {
+                num_str.push(ch);
+            } else {
+                break;
+            }
+            self.next_char();
+        }
 
-        self.iter_advance(num_str.len());
-        (TokenType::Number(num_str.parse::<f64>().unwrap_or_else(|_| {
-            panic!("Parse number token error: '{}'", num_str.clone())
-        })), num_str.clone())
+        let num = str_to_integer(&num_str).map(|num| num as f64)
+            .or_else(|| str_to_float(&num_str))
+            .ok_or_else(|| {
+                self.error(self.cur_line, format!("malformed number near '{}'", num_str))
+            })?;
+        Ok((TokenType::Number(num), num_str))
     }
 
-    fn parse_str(&mut self) -> (TokenType, String) {
-        let str_filter = |ch: &char| {
-            *ch != '"'
-        };
-        self.char_iter.next();
-        let str: String = self.take_word(str_filter);
+    fn parse_escape(&mut self, str: &mut Vec<u8>) -> LexResult<()> {
+        let line = self.cur_line;
+        let ch = self.next_char()
+            .ok_or_else(|| self.error(line, "unfinished string near <eof>".to_string()))?;
+        match ch {
+            'a' => str.push(0x07),
+            'b' => str.push(0x08),
+            'f' => str.push(0x0c),
+            'n' | '\n' => str.push(b'\n'),
+            'r' => str.push(b'\r'),
+            't' => str.push(b'\t'),
+            'v' => str.push(0x0b),
+            '\\' | '"' | '\'' => str.push(ch as u8),
+            'x' => {
+                let hex_str: String = (0..2).filter_map(|_| self.next_char()).collect();
+                let byte = u8::from_str_radix(&hex_str, 16).map_err(|_| {
+                    self.error(line, format!("hexadecimal digit expected near '\\x{}'", hex_str))
+                })?;
+                str.push(byte);
+            }
+            'z' => {
+                while self.peek_char().is_some_and(|ch| ch.is_whitespace()) {
+                    self.next_char();
+                }
+            }
+            'u' => {
+                if self.next_char() != Some('{') {
+                    return Err(self.error(line, "missing '{' in \\u{xxxx}".to_string()));
+                }
+                if !self.peek_char().is_some_and(|ch| ch.is_ascii_hexdigit()) {
+                    return Err(self.error(line, "hexadecimal digit expected".to_string()));
+                }
+                let mut code = 0u32;
+                while let Some(digit) = self.peek_char().and_then(|ch| ch.to_digit(16)) {
+                    if code > 0x7fff_ffff >> 4 {
+                        return Err(self.error(line, "UTF-8 value too large".to_string()));
+                    }
+                    code = code << 4 | digit;
+                    self.next_char();
+                }
+                if self.next_char() != Some('}') {
+                    return Err(self.error(line, "missing '}' in \\u{xxxx}".to_string()));
+                }
+                str.extend(utf8_escape(code));
+            }
+            _ if ch.is_ascii_digit() => {
+                let mut num = ch.to_digit(10).unwrap();
+                for _ in 0..2 {
+                    match self.peek_char().and_then(|ch| ch.to_digit(10)) {
+                        Some(digit) => {
+                            num = num * 10 + digit;
+                            self.next_char();
+                        }
+                        None => break,
+                    }
+                }
+                if num > 255 {
+                    return Err(self.error(line, format!("decimal escape too large near '\\{}'", num)));
+                }
+                str.push(num as u8);
+            }
+            _ => return Err(self.error(line, format!("invalid escape sequence '\\{}'", ch)))
+        }
+        Ok(())
+    }
+
+    fn parse_str(&mut self) -> LexResult<(TokenType, String)> {
+        let line = self.cur_line;
+        let start = self.pos;
+        let quote = self.next_char().unwrap();
+        let mut str = Vec::new();
+        loop {
+            match self.peek_char() {
+                Some(ch) if ch == quote => {
+                    self.next_char();
+                    break;
+                }
+                Some('\\') => {
+                    self.next_char();
+                    self.parse_escape(&mut str)?;
+                }
+                Some('\n') | Some('\r') | None => {
+                    let raw: String = self.chars[start..self.pos].iter().collect();
+                    return Err(self.error(line, format!("unfinished string near '{}'", raw)));
+                }
+                Some(ch) => {
+                    self.next_char();
+                    str.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+            }
+        }
+        let raw = self.chars[start..self.pos].iter().collect();
+        Ok((TokenType::String(str), raw))
+    }
 
-        self.char_iter.next();
-        self.iter_advance(str.len() + 2);
-        (TokenType::String(str.clone()), str.clone())
+    /// Returns the level of a long bracket (`[[`, `[==[`) starting at the
+    /// current position, without consuming anything.
+    fn long_bracket_level(&self) -> Option<usize> {
+        if self.peek_char() != Some('[') {
+            return None;
+        }
+        let level = self.chars[self.pos + 1..].iter().take_while(|ch| **ch == '=').count();
+        match self.peek_char_at(level + 1) {
+            Some('[') => Some(level),
+            _ => None
+        }
     }
 
-    fn parser_operator(&mut self) -> (TokenType, String) {
+    fn parse_long_str(&mut self, level: usize) -> LexResult<String> {
+        let line = self.cur_line;
+        let close: Vec<char> = format!("]{}]", "=".repeat(level)).chars().collect();
+        self.skip_chars(level + 2);
+        // a newline right after the opening bracket is not part of the string
+        if self.peek_char().is_some_and(Lexer::is_newline) {
+            self.next_char();
+        }
+        let mut str = String::new();
+        loop {
+            if self.chars[self.pos..].starts_with(&close) {
+                self.skip_chars(close.len());
+                return Ok(str);
+            }
+            match self.next_char() {
+                Some(ch) => str.push(ch),
+                None => return Err(self.error(line, "unfinished long string near <eof>".to_string()))
+            }
+        }
+    }
+
+    fn parser_operator(&mut self) -> LexResult<(TokenType, String)> {
         for len in (1..4).rev() {
-            let ope_str: String = self.char_iter.clone().take(len).collect();
+            let ope_str: String = self.peek_str(len);
             if let Some(key_word) = self.opt_hash_map.get(&ope_str).cloned() {
-                self.char_iter.by_ref().take(len).count();
-                self.iter_advance(len);
-                return (TokenType::OptKeyWord(key_word), ope_str);
+                self.skip_chars(len);
+                return Ok((TokenType::OptKeyWord(key_word), ope_str));
             }
         }
-        panic!("Parse operator token error at {}: {}", self.cur_line, self.cur_column)
-    }
-
-    fn skip_comment(&mut self) {
-        while if let Some(ch) = self.char_iter.peek() {
-            (|ch: &char| {
-                *ch != '\n'
-            })(ch)
-        } else {
-            false
-        } {
-            self.char_iter.next();
-        }
-        self.char_iter.next();
-        self.iter_new_line();
-    }
-
-    fn handle_sub_and_comment(&mut self, cur_line: usize, cur_column: usize) -> Option<Token> {
-        self.char_iter.next();
-        self.iter_advance(1);
-        let next_val = self.char_iter.peek().unwrap_or_else(|| { &'\n' });
-        if *next_val == '-' {
-            self.char_iter.next();
-            self.skip_comment();
-            return self.get_next_token();
-        }
-        Some(Token::new(TokenType::OptKeyWord(KeyWord::SUB), "-".to_string(), cur_line, cur_column))
-    }
-
-
-    fn get_next_token(&mut self) -> Option<Token> {
-        match self.char_iter.peek() {
-            Some(val) => {
-                let token_info: (TokenType, String);
-                let cur_line = self.cur_line;
-                let cur_column = self.cur_column;
-
-                if val.is_numeric() {
-                    token_info = self.parse_number();
-                } else if val.is_alphanumeric() || *val == '_' {
-                    token_info = self.parse_id();
-                } else if *val == '"' {
-                    token_info = self.parse_str();
-                } else if !val.is_ascii_graphic() {
-                    loop {
-                        match self.char_iter.peek() {
-                            Some(ch) => {
-                                if *ch == '\n' {
-                                    self.iter_new_line();
-                                } else if ch.is_ascii_graphic().not() {
-                                    self.iter_advance(1);
-                                } else {
-                                    break;
-                                }
-                            }
-                            None => {
-                                break;
-                            }
-                        }
-                        self.char_iter.next();
+        Err(self.error(self.cur_line, format!("unexpected symbol near '{}'", self.peek_char().unwrap())))
+    }
+
+    /// Skips a comment; the leading `--` has already been consumed.
+    fn skip_comment(&mut self) -> LexResult<()> {
+        if let Some(level) = self.long_bracket_level() {
+            self.parse_long_str(level)?;
+            return Ok(());
+        }
+        // the newline itself is left to the white space handling
+        while self.peek_char().is_some_and(|ch| !Lexer::is_newline(ch)) {
+            self.next_char();
+        }
+        Ok(())
+    }
+
+    fn get_next_token(&mut self) -> LexResult<Option<Token>> {
+        loop {
+            let val = match self.peek_char() {
+                Some(val) => val,
+                None => {
+                    if self.eof {
+                        return Ok(None);
                     }
-                    return self.get_next_token();
-                } else if *val == '-' {
-                    return self.handle_sub_and_comment(cur_line, cur_column);
-                } else {
-                    token_info = self.parser_operator();
-                }
-                Some(Token::new(token_info.0, token_info.1, cur_line, cur_column))
-            }
-            None => {
-                if !self.eof {
                     self.eof = true;
-                    Some(Token::eof())
-                } else {
-                    None
+                    return Ok(Some(Token::eof(self.cur_line, self.cur_column)));
                 }
-            }
+            };
+            let cur_line = self.cur_line;
+            let cur_column = self.cur_column;
+
+            let (token_type, raw_data) = if Lexer::is_white_space(val) {
+                self.next_char();
+                continue;
+            } else if val == '-' && self.peek_char_at(1) == Some('-') {
+                self.skip_chars(2);
+                self.skip_comment()?;
+                continue;
+            } else if val.is_ascii_digit()
+                || (val == '.' && self.peek_char_at(1).is_some_and(|ch| ch.is_ascii_digit())) {
+                self.parse_number()?
+            } else if val.is_ascii_alphabetic() || val == '_' {
+                self.parse_id()
+            } else if val == '"' || val == '\'' {
+                self.parse_str()?
+            } else if let Some(level) = self.long_bracket_level() {
+                let str = self.parse_long_str(level)?;
+                (TokenType::String(str.clone().into_bytes()), str)
+            } else {
+                self.parser_operator()?
+            };
+            return Ok(Some(Token::new(token_type, raw_data, cur_line, cur_column)));
         }
     }
 }
 
 impl Lexer {
-    pub fn peek_token_type(&mut self) -> TokenType {
+    pub fn chunk_name(&self) -> &str {
+        &self.chunk_name
+    }
+
+    /// Line of the last token taken by `next_token`.
+    pub fn last_line(&self) -> usize {
+        self.last_line
+    }
+
+    /// Returns the next token without consuming it. Past the end of the
+    /// source the EOF token is returned again.
+    pub fn peek_token(&mut self) -> LexResult<&Token> {
         if self.next_token.is_none() {
-            self.next_token = self.get_next_token();
+            self.next_token = match self.ahead_token.take() {
+                Some(token) => Some(token),
+                None => self.get_next_token()?
+            };
         }
-        self.next_token.unwrap().type_id
+        if self.next_token.is_none() {
+            self.next_token = Some(Token::eof(self.cur_line, self.cur_column));
+        }
+        Ok(self.next_token.as_ref().unwrap())
+    }
+
+    pub fn peek_token_type(&mut self) -> LexResult<TokenType> {
+        Ok(self.peek_token()?.type_id.clone())
+    }
+
+    /// Type of the token after the peeked one.
+    pub fn lookahead_token_type(&mut self) -> LexResult<TokenType> {
+        self.peek_token()?;
+        if self.ahead_token.is_none() {
+            self.ahead_token = self.get_next_token()?;
+        }
+        Ok(match self.ahead_token {
+            Some(ref token) => token.type_id.clone(),
+            None => TokenType::EOF
+        })
     }
 
-    pub fn next_token(&mut self) -> Option<Token> {
-        let ret_token = self.next_token.clone();
-        self.next_token = self.get_next_token();
-        ret_token
+    pub fn next_token(&mut self) -> LexResult<Token> {
+        self.peek_token()?;
+        let token = self.next_token.take().unwrap();
+        self.last_line = token.line;
+        Ok(token)
     }
 }
 
+/// Yields every token up to and including the EOF token, or stops after
+/// the first lexical error.
 impl Iterator for Lexer {
-    type Item = Token;
-    fn next(&mut self) -> Option<Token> {
-        self.get_next_token()
+    type Item = LexResult<Token>;
+    fn next(&mut self) -> Option<LexResult<Token>> {
+        match self.get_next_token() {
+            Ok(token) => token.map(Ok),
+            Err(err) => {
+                self.eof = true;
+                self.pos = self.chars.len();
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(code: &str) -> LexResult<Vec<Token>> {
+        Lexer::new(code, "test").collect()
     }
-}
\ No newline at end of file
+
+    fn strings(code: &str) -> Vec<Vec<u8>> {
+        tokens(code).unwrap().iter().filter_map(|token| token.get_str().cloned()).collect()
+    }
+
+    fn error(code: &str) -> String {
+        tokens(code).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn operators_take_longest_match() {
+        let types: Vec<TokenType> = tokens("a...b..c.d//e/f~=g~h::i<<j<=k").unwrap()
+            .into_iter().map(|token| token.type_id).collect();
+        let id = TokenType::ID(String::new());
+        assert_eq!(types, [
+            id.clone(), TokenType::from(KeyWord::VAR), id.clone(), TokenType::from(KeyWord::CON),
+            id.clone(), TokenType::from(KeyWord::DOT), id.clone(), TokenType::from(KeyWord::FDIV),
+            id.clone(), TokenType::from(KeyWord::DIV), id.clone(), TokenType::from(KeyWord::NEQ),
+            id.clone(), TokenType::from(KeyWord::BXOR), id.clone(), TokenType::from(KeyWord::PATH),
+            id.clone(), TokenType::from(KeyWord::LSH), id.clone(), TokenType::from(KeyWord::LEE),
+            id, TokenType::EOF,
+        ]);
+    }
+
+    #[test]
+    fn numbers() {
+        let nums: Vec<f64> = tokens("3 0x10 1e2 .5 3. 0x1p-2 0xA.8").unwrap().iter()
+            .filter_map(|token| token.get_num().cloned()).collect();
+        assert_eq!(nums, [3.0, 16.0, 100.0, 0.5, 3.0, 0.25, 10.5]);
+        assert_eq!(error("x = 3..2"), "test:1: malformed number near '3..2'");
+        assert_eq!(error("x = 0xg"), "test:1: malformed number near '0xg'");
+        assert_eq!(error("x = 12abc"), "test:1: malformed number near '12abc'");
+    }
+
+    #[test]
+    fn short_strings_and_escapes() {
+        assert_eq!(strings(r#"'a\tb' "q\"\\" '\65\x42\u{43}' 'a\z
+              b' "line\
+next""#), [&b"a\tb"[..], b"q\"\\", b"ABC", b"ab", b"line\nnext"]);
+        assert_eq!(error("x = 'abc"), "test:1: unfinished string near ''abc'");
+        assert_eq!(error("x = 'abc\n'"), "test:1: unfinished string near ''abc'");
+        assert_eq!(error(r"x = '\q'"), r"test:1: invalid escape sequence '\q'");
+        assert_eq!(error(r"x = '\300'"), r"test:1: decimal escape too large near '\300'");
+    }
+
+    #[test]
+    fn strings_are_bytes() {
+        assert_eq!(strings(r"'\xe4\xb8\xad' '\228\184\173' '\u{4e2d}' '中'"), ["中".as_bytes(); 4]);
+        assert_eq!(strings(r"'\xff\0' '\u{d800}' '\u{7FFFFFFF}'"), [
+            &[0xff, 0][..], &[0xed, 0xa0, 0x80], &[0xfd, 0xbf, 0xbf, 0xbf, 0xbf, 0xbf],
+        ]);
+        assert_eq!(error(r"x = '\u{80000000}'"), "test:1: UTF-8 value too large");
+        assert_eq!(error(r"x = '\u{}'"), "test:1: hexadecimal digit expected");
+    }
+
+    #[test]
+    fn long_strings() {
+        assert_eq!(strings("[[\nfirst\nsecond]] [==[a]]b]=]c]==] [[]]"),
+                   [&b"first\nsecond"[..], b"a]]b]=]c", b""]);
+        assert_eq!(error("x = [==[abc]]"), "test:1: unfinished long string near <eof>");
+    }
+
+    #[test]
+    fn comments_and_line_numbers() {
+        let tokens = tokens("a -- comment\n--[[ long\ncomment ]] b --[==[\n]==]\nc -- last").unwrap();
+        let lines: Vec<(String, usize)> = tokens.iter()
+            .map(|token| (token.raw_data.clone(), token.line)).collect();
+        assert_eq!(lines, [
+            ("a".to_string(), 1), ("b".to_string(), 3), ("c".to_string(), 5), ("<eof>".to_string(), 5),
+        ]);
+        assert_eq!(error("--[[ unfinished"), "test:1: unfinished long string near <eof>");
+    }
+
+    #[test]
+    fn line_endings() {
+        for newline in ["\n", "\r", "\r\n", "\n\r"].iter() {
+            let code = format!("a{0}'b\\{0}c'{0}[[{0}d{0}e]]{0}-- f{0}g", newline);
+            let tokens = tokens(&code).unwrap();
+            let lines: Vec<usize> = tokens.iter().map(|token| token.line).collect();
+            assert_eq!(lines, [1, 2, 4, 8, 8], "{:?}", newline);
+            assert_eq!(tokens[1].get_str().unwrap(), b"b\nc", "{:?}", newline);
+            assert_eq!(tokens[2].get_str().unwrap(), b"d\ne", "{:?}", newline);
+        }
+        assert_eq!(tokens("a\n\n\r\rb").unwrap()[1].line, 4);
+        assert_eq!(error("x = 'abc\r'"), "test:1: unfinished string near ''abc'");
+    }
+
+    #[test]
+    fn many_comments_do_not_recurse() {
+        let code = "-- c\n".repeat(200_000);
+        let tokens = tokens(&code).unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].line, 200_001);
+    }
+
+    #[test]
+    fn only_white_space_is_skipped() {
+        assert!(tokens(" \t\r\n\u{0b}\u{0c}x").is_ok());
+        assert_eq!(error("x = 1 €"), "test:1: unexpected symbol near '€'");
+        assert_eq!(error("x = 1 \u{a0}"), "test:1: unexpected symbol near '\u{a0}'");
+        assert_eq!(error("x = $"), "test:1: unexpected symbol near '$'");
+        assert_eq!(error("é = 1"), "test:1: unexpected symbol near 'é'");
+        assert_eq!(error("xé = 1"), "test:1: unexpected symbol near 'é'");
+        assert_eq!(error("x = 1é"), "test:1: unexpected symbol near 'é'");
+    }
+
+    #[test]
+    fn lookahead_keeps_order() {
+        let mut lexer = Lexer::new("a = 1", "test");
+        assert_eq!(lexer.peek_token().unwrap().raw_data, "a");
+        assert_eq!(lexer.lookahead_token_type().unwrap(), TokenType::from(KeyWord::ASS));
+        assert_eq!(lexer.next_token().unwrap().raw_data, "a");
+        assert_eq!(lexer.next_token().unwrap().raw_data, "=");
+        assert_eq!(lexer.next_token().unwrap().raw_data, "1");
+        assert_eq!(lexer.next_token().unwrap().type_id, TokenType::EOF);
+        assert_eq!(lexer.next_token().unwrap().type_id, TokenType::EOF);
+    }
+}
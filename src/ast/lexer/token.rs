@@ -3,7 +3,7 @@ use crate::string_hash_map;
 use std::fmt::{Display, Formatter, Result, Debug};
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum KeyWord {
     ADD,
     SUB,
@@ -38,6 +38,10 @@ pub enum KeyWord {
     SEM,
     COL,
     PATH,
+    BAND,
+    BOR,
+    BXOR,
+    VAR,
 
     BRK,
     DO,
@@ -62,7 +66,7 @@ pub enum KeyWord {
 
 impl KeyWord {
     pub fn is_binary_operator(&self) -> bool {
-        match *self {
+        matches!(*self,
             KeyWord::ADD |
             KeyWord::SUB |
             KeyWord::MUL |
@@ -72,7 +76,6 @@ impl KeyWord {
             KeyWord::RSH |
             KeyWord::MOD |
             KeyWord::POW |
-            KeyWord::ASS |
             KeyWord::EQU |
             KeyWord::NEQ |
             KeyWord::GR |
@@ -80,34 +83,31 @@ impl KeyWord {
             KeyWord::GRE |
             KeyWord::LEE |
             KeyWord::CON |
-            KeyWord::DOT |
-            KeyWord::COM |
+            KeyWord::BAND |
+            KeyWord::BOR |
+            KeyWord::BXOR |
             KeyWord::AND |
-            KeyWord::OR => true,
-            _ => false
-        }
+            KeyWord::OR)
     }
 
     pub fn is_unique_operator(&self) -> bool {
-        match *self {
+        matches!(*self,
             KeyWord::LEN |
             KeyWord::MIN |
-            KeyWord::NOT => true,
-            _ => false
-        }
+            KeyWord::SUB |
+            KeyWord::BXOR |
+            KeyWord::NOT)
     }
 
     pub fn is_divide_operator(&self) -> bool {
-        match *self {
+        matches!(*self,
             KeyWord::LSM |
             KeyWord::RSM |
             KeyWord::LMI |
             KeyWord::RMI |
             KeyWord::LLA |
             KeyWord::RLA |
-            KeyWord::PATH => true,
-            _ => false
-        }
+            KeyWord::PATH)
     }
 
     pub fn get_display_str(&self) -> &'static str {
@@ -145,6 +145,10 @@ impl KeyWord {
             KeyWord::SEM => ";",
             KeyWord::COL => ":",
             KeyWord::PATH => "::",
+            KeyWord::BAND => "&",
+            KeyWord::BOR => "|",
+            KeyWord::BXOR => "~",
+            KeyWord::VAR => "...",
 
             KeyWord::BRK => "break",
             KeyWord::DO => "do",
@@ -169,18 +173,12 @@ impl KeyWord {
     }
 }
 
-impl PartialEq<KeyWord> for KeyWord {
-    fn eq(&self, key_word: &KeyWord) -> bool {
-        *key_word == *self
-    }
-}
-
 #[allow(dead_code)]
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Clone)]
 pub enum TokenType {
     OptKeyWord(KeyWord),
     ID(String),
-    String(String),
+    String(Vec<u8>),
     Number(f64),
     EOF,
 }
@@ -195,10 +193,10 @@ impl TokenType {
     pub fn to_str(&self) -> &'static str {
         match self {
             TokenType::OptKeyWord(key_word) => key_word.get_display_str(),
-            TokenType::Number(num) => "num",
-            TokenType::String(str) => "str",
-            TokenType::ID(str) => "id",
-            _ => "eof"
+            TokenType::Number(_) => "num",
+            TokenType::String(_) => "str",
+            TokenType::ID(_) => "id",
+            _ => "<eof>"
         }
     }
 }
@@ -262,13 +260,19 @@ impl Token {
         }
     }
 
+    pub fn get_str(&self) -> Option<&Vec<u8>> {
+        match self.type_id {
+            TokenType::String(ref str) => Some(str),
+            _ => None
+        }
+    }
 
-    pub fn eof() -> Token {
+    pub fn eof(line: usize, column: usize) -> Token {
         Token {
             type_id: TokenType::EOF,
-            raw_data: "".to_string(),
-            line: 0,
-            column: 0,
+            raw_data: "<eof>".to_string(),
+            line,
+            column,
         }
     }
 }
@@ -315,7 +319,6 @@ pub fn get_opt_map() -> HashMap<String, KeyWord> {
         "and"   =>  KeyWord::AND,
         "or"    =>  KeyWord::OR,
         "#"     =>  KeyWord::LEN,
-        "-"     =>  KeyWord::MIN,
         "not"   =>  KeyWord::NOT,
         "("     =>  KeyWord::LSM,
         ")"     =>  KeyWord::RSM,
@@ -326,7 +329,11 @@ pub fn get_opt_map() -> HashMap<String, KeyWord> {
         ","     =>  KeyWord::COM,
         ";"     =>  KeyWord::SEM,
         ":"     =>  KeyWord::COL,
-        "::"    =>  KeyWord::PATH
+        "::"    =>  KeyWord::PATH,
+        "&"     =>  KeyWord::BAND,
+        "|"     =>  KeyWord::BOR,
+        "~"     =>  KeyWord::BXOR,
+        "..."   =>  KeyWord::VAR
     ]
 }
 
@@ -350,6 +357,9 @@ pub fn get_key_word_map() -> HashMap<String, KeyWord> {
         "true"      =>  KeyWord::TRU,
         "until"     =>  KeyWord::UNT,
         "while"     =>  KeyWord::WHI,
-        "goto"      =>  KeyWord::GOT
+        "goto"      =>  KeyWord::GOT,
+        "and"       =>  KeyWord::AND,
+        "or"        =>  KeyWord::OR,
+        "not"       =>  KeyWord::NOT
     ]
 }
\ No newline at end of file
@@ -36,3 +36,166 @@ impl<'a, T> CondTake<'a, T> for &'a mut Peekable<T> where T: Iterator {
         }
     }
 }
+
+fn hex_digit(ch: char) -> Option<u32> {
+    ch.to_digit(16)
+}
+
+pub fn str_to_integer(num_str: &str) -> Option<i64> {
+    let (is_neg, num_str) = match num_str.trim().strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, num_str.trim()),
+    };
+    let num = if let Some(hex_str) = num_str.strip_prefix("0x")
+        .or_else(|| num_str.strip_prefix("0X")) {
+        // hexadecimal integers wrap around on overflow
+        if hex_str.is_empty() {
+            return None;
+        }
+        hex_str.chars().try_fold(0i64, |acc, ch| {
+            hex_digit(ch).map(|digit| acc.wrapping_mul(16).wrapping_add(digit as i64))
+        })?
+    } else {
+        // decimal integers that overflow are read as floats instead
+        if num_str.is_empty() || !num_str.chars().all(|ch| ch.is_ascii_digit()) {
+            return None;
+        }
+        num_str.parse::<i64>().ok()?
+    };
+    Some(if is_neg { num.wrapping_neg() } else { num })
+}
+
+fn hex_str_to_float(hex_str: &str) -> Option<f64> {
+    let (mantissa_str, exp_str) = match hex_str.find(['p', 'P']) {
+        Some(pos) => (&hex_str[..pos], Some(&hex_str[pos + 1..])),
+        None => (hex_str, None),
+    };
+    let mut mantissa = 0f64;
+    let mut exp = 0i64;
+    let mut seen_dot = false;
+    let mut any_digit = false;
+    for ch in mantissa_str.chars() {
+        if ch == '.' {
+            if seen_dot {
+                return None;
+            }
+            seen_dot = true;
+        } else {
+            mantissa = mantissa * 16.0 + hex_digit(ch)? as f64;
+            any_digit = true;
+            if seen_dot {
+                exp -= 4;
+            }
+        }
+    }
+    if !any_digit {
+        return None;
+    }
+    if let Some(exp_str) = exp_str {
+        exp += exp_str.parse::<i64>().ok()?;
+    }
+    Some(mantissa * 2f64.powi(exp.clamp(i32::MIN as i64, i32::MAX as i64) as i32))
+}
+
+pub fn str_to_float(num_str: &str) -> Option<f64> {
+    let (is_neg, num_str) = match num_str.trim().strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, num_str.trim()),
+    };
+    let num = if let Some(hex_str) = num_str.strip_prefix("0x")
+        .or_else(|| num_str.strip_prefix("0X")) {
+        hex_str_to_float(hex_str)?
+    } else {
+        // reject the spellings Rust accepts but Lua does not
+        if !num_str.starts_with(|ch: char| ch.is_ascii_digit() || ch == '.')
+            || num_str.contains(|ch: char| ch.is_ascii_alphabetic() && ch != 'e' && ch != 'E') {
+            return None;
+        }
+        num_str.parse::<f64>().ok()?
+    };
+    Some(if is_neg { -num } else { num })
+}
+
+/// Encodes a `\u{XXX}` escape the way Lua does: code points up to
+/// 0x7FFFFFFF, surrogates included, with the original UTF-8 scheme of up to
+/// six bytes.
+pub fn utf8_escape(code: u32) -> Vec<u8> {
+    if code < 0x80 {
+        return vec![code as u8];
+    }
+    let mut bytes = Vec::new();
+    let mut code = code;
+    // largest value that still fits in the first byte
+    let mut first_max = 0x3f;
+    while code > first_max {
+        bytes.push(0x80 | (code & 0x3f) as u8);
+        code >>= 6;
+        first_max >>= 1;
+    }
+    bytes.push(((!first_max << 1) | code) as u8);
+    bytes.reverse();
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        assert_eq!(str_to_integer("42"), Some(42));
+        assert_eq!(str_to_integer("-42"), Some(-42));
+        assert_eq!(str_to_integer("0xff"), Some(255));
+        assert_eq!(str_to_integer("0XFF"), Some(255));
+        assert_eq!(str_to_integer("9223372036854775807"), Some(i64::MAX));
+        assert_eq!(str_to_integer("0x"), None);
+        assert_eq!(str_to_integer("1.0"), None);
+        assert_eq!(str_to_integer("1e2"), None);
+    }
+
+    #[test]
+    fn hex_integers_wrap_around() {
+        assert_eq!(str_to_integer("0xffffffffffffffff"), Some(-1));
+        assert_eq!(str_to_integer("0x7fffffffffffffff"), Some(i64::MAX));
+        assert_eq!(str_to_integer("0x10000000000000000"), Some(0));
+    }
+
+    #[test]
+    fn decimal_overflow_is_a_float() {
+        assert_eq!(str_to_integer("9223372036854775808"), None);
+        assert_eq!(str_to_float("9223372036854775808"), Some(9223372036854775808.0));
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(str_to_float("1.5"), Some(1.5));
+        assert_eq!(str_to_float(".5"), Some(0.5));
+        assert_eq!(str_to_float("3."), Some(3.0));
+        assert_eq!(str_to_float("1e-2"), Some(0.01));
+        assert_eq!(str_to_float("0x1p-2"), Some(0.25));
+        assert_eq!(str_to_float("0x1P4"), Some(16.0));
+        assert_eq!(str_to_float("0x.8"), Some(0.5));
+        assert_eq!(str_to_float("0xA.8p1"), Some(21.0));
+        assert_eq!(str_to_float("-2.5"), Some(-2.5));
+    }
+
+    #[test]
+    fn malformed_floats() {
+        assert_eq!(str_to_float("3..2"), None);
+        assert_eq!(str_to_float("0x1p"), None);
+        assert_eq!(str_to_float("0x.p1"), None);
+        assert_eq!(str_to_float("1e"), None);
+        assert_eq!(str_to_float("inf"), None);
+        assert_eq!(str_to_float("nan"), None);
+        assert_eq!(str_to_float("infinity"), None);
+    }
+
+    #[test]
+    fn utf8_escapes() {
+        assert_eq!(utf8_escape(0x41), b"A");
+        assert_eq!(utf8_escape(0x4e2d), "中".as_bytes());
+        assert_eq!(utf8_escape(0x10ffff), "\u{10ffff}".as_bytes());
+        assert_eq!(utf8_escape(0xd800), [0xed, 0xa0, 0x80]);
+        assert_eq!(utf8_escape(0x7fffffff), [0xfd, 0xbf, 0xbf, 0xbf, 0xbf, 0xbf]);
+    }
+}
@@ -1,3 +1,4 @@
+pub mod error;
 pub mod lexer;
 pub mod parser;
-pub mod ast_def;
\ No newline at end of file
+pub mod ast_def;
@@ -1,22 +1,56 @@
+use crate::ast::error::SyntaxError;
 use crate::ast::lexer::Lexer;
 use crate::ast::lexer::token::{Token, TokenType, KeyWord};
-use std::fmt::{Display, Formatter};
+use crate::ast::lexer::util::str_to_integer;
 use crate::ast::ast_def::stmt_def::block_def::Block;
 use crate::ast::ast_def::stmt_def::{Stat, Exp, StatType};
 use crate::ast::ast_def::stmt_def::stat_def::*;
-use crate::ast::ast_def::stmt_def::exp_def::{TrueExp, IntegerExp, FuncDefExp};
+use crate::ast::ast_def::stmt_def::exp_def::*;
 
 macro_rules! tk_from_kw {
     ($KEYWORD: expr) => { TokenType::from($KEYWORD) }
 }
 
+/// The error is boxed to keep results small: every nested block or
+/// expression adds a few frames to the stack, and unoptimized builds give
+/// each temporary result its own slot.
+pub type ParseResult<T> = Result<T, Box<SyntaxError>>;
+
+/// Limit on nested blocks and expressions, the same as the C-level limit of
+/// the reference implementation. The functions on the recursive paths are
+/// kept small so that this many levels fit in a 1MB stack even in debug
+/// builds; the work of each construct is done in helpers that return before
+/// the recursion goes deeper.
+const MAX_SYNTAX_LEVELS: usize = 200;
+
+/// A prefix expression before it is boxed, so statements can still tell
+/// calls and assignable expressions apart.
+enum PrefixExp {
+    Name(Box<IDExp>),
+    Parens(Box<ParensExp>),
+    Access(Box<TableAccessExp>),
+    Call(Box<FuncCallExp>),
+}
+
+impl PrefixExp {
+    fn into_exp(self) -> Box<dyn Exp> {
+        match self {
+            PrefixExp::Name(exp) => exp,
+            PrefixExp::Parens(exp) => exp,
+            PrefixExp::Access(exp) => exp,
+            PrefixExp::Call(exp) => exp,
+        }
+    }
+}
+
 pub struct Parser {
     lexer: Lexer,
+    levels: usize,
 }
 
 impl Parser {
-    fn new(source_code: &String) -> Parser {
-        Parser { lexer: Lexer::new(source_code) }
+    pub fn new(source_code: &str, chunk_name: &str) -> Parser {
+        Parser { lexer: Lexer::new(source_code, chunk_name), levels: 0 }
     }
 }
 
@@ -24,263 +58,883 @@ impl Parser {
     fn is_ret_or_block_end(token_type: &TokenType) -> bool {
         match token_type {
             TokenType::OptKeyWord(key_word) => {
-                match key_word {
+                matches!(key_word,
                     KeyWord::RET |
                     KeyWord::END |
                     KeyWord::ELI |
                     KeyWord::ELS |
-                    KeyWord::UNT => true,
-                    _ => false
-                }
+                    KeyWord::UNT)
             }
             TokenType::EOF => true,
             _ => false
         }
     }
 
-    fn expected_token(&mut self, expected_type: TokenType) {
-        if !self.lexer.peek_token_type().eq(&expected_type) {
-            panic!("expected: {}", expected_type);
+    fn peek_token(&mut self) -> ParseResult<Token> {
+        Ok(self.lexer.peek_token()?.clone())
+    }
+
+    fn peek_line(&mut self) -> ParseResult<usize> {
+        Ok(self.lexer.peek_token()?.line)
+    }
+
+    fn peek_key_word(&mut self, key_word: KeyWord) -> ParseResult<bool> {
+        Ok(self.lexer.peek_token_type()?.eq(&tk_from_kw!(key_word)))
+    }
+
+    /// Key word or operator of the next token, if it is one.
+    fn peek_op(&mut self) -> ParseResult<Option<KeyWord>> {
+        Ok(self.lexer.peek_token()?.get_key_word().cloned())
+    }
+
+    fn skip_token(&mut self) -> ParseResult<()> {
+        self.lexer.next_token()?;
+        Ok(())
+    }
+
+    /// Consumes the next token and returns its line.
+    fn next_line(&mut self) -> ParseResult<usize> {
+        Ok(self.lexer.next_token()?.line)
+    }
+
+    fn error_near<T>(&mut self, msg: &str) -> ParseResult<T> {
+        let token = self.peek_token()?;
+        let near = match token.type_id {
+            TokenType::EOF => token.raw_data,
+            _ => format!("'{}'", token.raw_data)
+        };
+        Err(Box::new(SyntaxError::new(self.lexer.chunk_name(), token.line, format!("{} near {}", msg, near))))
+    }
+
+    fn enter_level(&mut self) -> ParseResult<()> {
+        self.levels += 1;
+        if self.levels > MAX_SYNTAX_LEVELS {
+            return self.error_near("chunk has too many syntax levels");
+        }
+        Ok(())
+    }
+
+    fn leave_level(&mut self) {
+        self.levels -= 1;
+    }
+
+    fn expected_token(&mut self, expected_type: TokenType) -> ParseResult<Token> {
+        if !self.lexer.peek_token_type()?.eq(&expected_type) {
+            return self.error_near(&format!("'{}' expected", expected_type));
+        }
+        self.lexer.next_token()
+    }
+
+    /// Like `expected_token` for a key word, returning only the line.
+    fn expected_key_word(&mut self, key_word: KeyWord) -> ParseResult<usize> {
+        Ok(self.expected_token(tk_from_kw!(key_word))?.line)
+    }
+
+    fn expected_match(&mut self, what: KeyWord, who: KeyWord, line: usize) -> ParseResult<()> {
+        if !self.peek_key_word(what.clone())? {
+            if line == self.peek_line()? {
+                return self.error_near(&format!("'{}' expected", what.get_display_str()));
+            }
+            return self.error_near(&format!("'{}' expected (to close '{}' at line {})",
+                                            what.get_display_str(), who.get_display_str(), line));
         }
-        self.lexer.next_token();
+        self.skip_token()?;
+        Ok(())
     }
 
-    fn expected_id(&mut self) -> Token {
-        match self.lexer.peek_token_type() {
-            TokenType::ID(_) => self.lexer.next_token().unwrap(),
-            _ => panic!("expected id")
+    fn expected_id(&mut self) -> ParseResult<Token> {
+        match self.lexer.peek_token_type()? {
+            TokenType::ID(_) => self.lexer.next_token(),
+            _ => self.error_near("<name> expected")
         }
     }
+
+    fn expected_name_exp(&mut self) -> ParseResult<Box<StringExp>> {
+        let token = self.expected_id()?;
+        Ok(Box::new(StringExp { line: token.line, str: token.raw_data.into_bytes() }))
+    }
 }
 
 impl Parser {
-    fn parse_stat(&mut self) -> Box<dyn Stat> {
-        match self.lexer.peek_token_type() {
-            TokenType::OptKeyWord(key_word) => {
-                match key_word {
-                    KeyWord::SEM => self.parse_empty_stat(),
-                    KeyWord::BRK => self.parse_break_stat(),
-                    KeyWord::PATH => self.parse_label_stat(),
-                    KeyWord::GOT => self.parse_goto_stat(),
-                    KeyWord::DO => self.parse_do_stat(),
-                    KeyWord::WHI => self.parse_while_stat(),
-                    KeyWord::REP => self.parse_repeat_stat(),
-                    KeyWord::IF => self.parse_if_stat(),
-                    KeyWord::FOR => self.parse_for_stat(),
-                    KeyWord::FUN => self.parse_func_def_stat(),
-                    KeyWord::LOC => self.parse_local_stat(),
-                    _ => self.parse_func_call_or_assign_stat()
-                }
-            }
+    fn parse_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        self.enter_level()?;
+        let stat = match self.peek_op()? {
+            Some(KeyWord::SEM) => self.parse_empty_stat(),
+            Some(KeyWord::BRK) => self.parse_break_stat(),
+            Some(KeyWord::PATH) => self.parse_label_stat(),
+            Some(KeyWord::GOT) => self.parse_goto_stat(),
+            Some(KeyWord::DO) => self.parse_do_stat(),
+            Some(KeyWord::WHI) => self.parse_while_stat(),
+            Some(KeyWord::REP) => self.parse_repeat_stat(),
+            Some(KeyWord::IF) => self.parse_if_stat(),
+            Some(KeyWord::FOR) => self.parse_for_stat(),
+            Some(KeyWord::FUN) => self.parse_func_def_stat(),
+            Some(KeyWord::LOC) => self.parse_local_stat(),
             _ => self.parse_func_call_or_assign_stat()
-        }
+        }?;
+        self.leave_level();
+        Ok(stat)
     }
 
-    fn parse_empty_stat(&mut self) -> Box<dyn Stat> {
-        self.expected_token(tk_from_kw!(KeyWord::SEM));
-        Box::new(EmptyStat {})
-    }
-    fn parse_break_stat(&mut self) -> Box<dyn Stat> {
-        self.expected_token(tk_from_kw!(KeyWord::BRK));
-        Box::new(BreakStat { line: 0 })
-    }
-    fn parse_label_stat(&mut self) -> Box<dyn Stat> {
-        self.expected_token(tk_from_kw!(KeyWord::PATH));
-        let name = self.expected_id().raw_data;
-        self.expected_token(tk_from_kw!(KeyWord::PATH));
-        Box::new(LabelStat { name })
-    }
-    fn parse_goto_stat(&mut self) -> Box<dyn Stat> {
-        self.expected_token(tk_from_kw!(KeyWord::GOT));
-        let name = self.expected_id().raw_data;
-        Box::new(GotoStat { target: name })
-    }
-    fn parse_do_stat(&mut self) -> Box<dyn Stat> {
-        self.expected_token(tk_from_kw!(KeyWord::DO));
-        let block = self.parse();
-        self.expected_token(tk_from_kw!(KeyWord::END));
-        Box::new(DoStat { block })
-    }
-    fn parse_while_stat(&mut self) -> Box<dyn Stat> {
-        self.expected_token(tk_from_kw!(KeyWord::WHI));
-        let exp = self.parse_exp();
-        self.expected_token(tk_from_kw!(KeyWord::DO));
-        let block = self.parse();
-        self.expected_token(tk_from_kw!(KeyWord::END));
-        Box::new(WhileStat { exp, block })
-    }
-    fn parse_repeat_stat(&mut self) -> Box<dyn Stat> {
-        self.expected_token(tk_from_kw!(KeyWord::REP));
-        let block = self.parse();
-        self.expected_token(tk_from_kw!(KeyWord::UNT));
-        let exp = self.parse_exp();
-        Box::new(RepeatStat { block, exp })
-    }
-    fn parse_if_stat(&mut self) -> Box<dyn Stat> {
+    fn parse_empty_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        self.expected_key_word(KeyWord::SEM)?;
+        Ok(Box::new(EmptyStat {}))
+    }
+    fn parse_break_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        let line = self.expected_key_word(KeyWord::BRK)?;
+        Ok(Box::new(BreakStat { line }))
+    }
+    fn parse_label_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        let line = self.expected_key_word(KeyWord::PATH)?;
+        let name = self.expected_id()?.raw_data;
+        self.expected_key_word(KeyWord::PATH)?;
+        Ok(Box::new(LabelStat { line, name }))
+    }
+    fn parse_goto_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        let line = self.expected_key_word(KeyWord::GOT)?;
+        let name = self.expected_id()?.raw_data;
+        Ok(Box::new(GotoStat { line, target: name }))
+    }
+    fn parse_do_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        let line = self.expected_key_word(KeyWord::DO)?;
+        let block = self.parse()?;
+        self.expected_match(KeyWord::END, KeyWord::DO, line)?;
+        Ok(Box::new(DoStat { block }))
+    }
+    fn parse_while_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        let line = self.expected_key_word(KeyWord::WHI)?;
+        let exp = self.parse_exp()?;
+        self.expected_key_word(KeyWord::DO)?;
+        let block = self.parse()?;
+        self.expected_match(KeyWord::END, KeyWord::WHI, line)?;
+        Ok(Box::new(WhileStat { exp, block }))
+    }
+    fn parse_repeat_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        let line = self.expected_key_word(KeyWord::REP)?;
+        let block = self.parse()?;
+        self.expected_match(KeyWord::UNT, KeyWord::REP, line)?;
+        let exp = self.parse_exp()?;
+        Ok(Box::new(RepeatStat { block, exp }))
+    }
+    fn parse_if_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
         let mut exps = Vec::new();
         let mut blocks = Vec::new();
-        self.expected_token(tk_from_kw!(KeyWord::IF));
-        exps.push(self.parse_exp());
-        self.expected_token(tk_from_kw!(KeyWord::THE));
-        blocks.push(self.parse());
-
-        while self.lexer.peek_token_type().eq(&tk_from_kw!(KeyWord::ELI)) {
-            self.lexer.next_token();
-            exps.push(self.parse_exp());
-            self.expected_token(tk_from_kw!(KeyWord::THE));
-            blocks.push(self.parse());
+        let line = self.expected_key_word(KeyWord::IF)?;
+        self.parse_cond_block(&mut exps, &mut blocks)?;
+        while self.peek_key_word(KeyWord::ELI)? {
+            self.skip_token()?;
+            self.parse_cond_block(&mut exps, &mut blocks)?;
         }
-
-        if self.lexer.peek_token_type().eq(&tk_from_kw!(KeyWord::ELS)) {
-            exps.push(Box::new(TrueExp { line: 0 }));
-            blocks.push(self.parse());
+        if self.peek_key_word(KeyWord::ELS)? {
+            let else_line = self.next_line()?;
+            exps.push(Box::new(TrueExp { line: else_line }));
+            blocks.push(self.parse()?);
         }
-
-        Box::new(IfStat { exps, blocks })
+        self.expected_match(KeyWord::END, KeyWord::IF, line)?;
+        Ok(Box::new(IfStat { exps, blocks }))
     }
-    fn parse_for_stat(&mut self) -> Box<dyn Stat> {
-        self.expected_token(tk_from_kw!(KeyWord::FOR));
-        let name = self.expected_id().raw_data;
-        if self.lexer.peek_token_type().eq(&tk_from_kw!(KeyWord::ASS)) {
-            self.parse_step_for_stat(name)
-        } else {
-            self.parse_range_for_stat(name)
-        }
+    /// `exp then block` of an `if` or `elseif`.
+    fn parse_cond_block(&mut self, exps: &mut Vec<Box<dyn Exp>>, blocks: &mut Vec<Block>) -> ParseResult<()> {
+        exps.push(self.parse_exp()?);
+        self.expected_key_word(KeyWord::THE)?;
+        blocks.push(self.parse()?);
+        Ok(())
     }
-    fn parse_step_for_stat(&mut self, first_val: String) -> Box<dyn Stat> {
-        self.expected_token(tk_from_kw!(KeyWord::ASS));
-        let init_exp = self.parse_exp();
-        self.expected_token(tk_from_kw!(KeyWord::COM));
-        let lim_exp = self.parse_exp();
-
-        let step_exp: Box<dyn Exp>;
-
-        if self.lexer.peek_token_type().eq(&tk_from_kw!(KeyWord::COM)) {
-            self.lexer.next_token();
-            step_exp = self.parse_exp();
+    fn parse_for_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        let line = self.expected_key_word(KeyWord::FOR)?;
+        let name = self.expected_id()?.raw_data;
+        if self.peek_key_word(KeyWord::ASS)? {
+            self.parse_step_for_stat(line, name)
         } else {
-            step_exp = Box::new(IntegerExp { line: 0, num: 1 });
+            self.parse_range_for_stat(line, name)
         }
-
-        self.expected_token(tk_from_kw!(KeyWord::DO));
-        let block = self.parse();
-        self.expected_token(tk_from_kw!(KeyWord::END));
-
-        Box::new(StepForStat {
-            beg_line: 0,
-            block_beg_line: 0,
-            var_name: name,
+    }
+    fn parse_step_for_stat(&mut self, beg_line: usize, first_val: String) -> ParseResult<Box<dyn Stat>> {
+        let [init_exp, lim_exp, step_exp] = self.parse_for_range()?;
+        let block_beg_line = self.expected_key_word(KeyWord::DO)?;
+        let block = self.parse()?;
+        self.expected_match(KeyWord::END, KeyWord::FOR, beg_line)?;
+        Ok(Box::new(StepForStat {
+            beg_line,
+            block_beg_line,
+            var_name: first_val,
             init_exp,
             lim_exp,
             step_exp,
             block,
-        })
+        }))
+    }
+    /// `= init, limit [, step]` of a numeric `for`.
+    fn parse_for_range(&mut self) -> ParseResult<[Box<dyn Exp>; 3]> {
+        self.expected_key_word(KeyWord::ASS)?;
+        let init_exp = self.parse_exp()?;
+        self.expected_key_word(KeyWord::COM)?;
+        let lim_exp = self.parse_exp()?;
+        let step_exp: Box<dyn Exp> = if self.peek_key_word(KeyWord::COM)? {
+            self.skip_token()?;
+            self.parse_exp()?
+        } else {
+            Box::new(IntegerExp { line: self.peek_line()?, num: 1 })
+        };
+        Ok([init_exp, lim_exp, step_exp])
+    }
+    fn parse_range_for_stat(&mut self, beg_line: usize, first_val: String) -> ParseResult<Box<dyn Stat>> {
+        let mut name_list = vec![first_val];
+        let exp_list = self.parse_for_in(&mut name_list)?;
+        let block_beg_line = self.expected_key_word(KeyWord::DO)?;
+        let block = self.parse()?;
+        self.expected_match(KeyWord::END, KeyWord::FOR, beg_line)?;
+        Ok(Box::new(RangeForStat { block_beg_line, name_list, exp_list, block }))
+    }
+    /// `{, Name} in explist` of a generic `for`.
+    fn parse_for_in(&mut self, name_list: &mut Vec<String>) -> ParseResult<Vec<Box<dyn Exp>>> {
+        while self.peek_key_word(KeyWord::COM)? {
+            self.skip_token()?;
+            name_list.push(self.expected_id()?.raw_data);
+        }
+        self.expected_key_word(KeyWord::IN)?;
+        self.parse_exp_list()
+    }
+
+    /// `function a.b:c() end` is sugar for `a.b.c = function(self) end`.
+    fn parse_func_def_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        let (var, is_method) = self.parse_func_name()?;
+        let mut func_def = self.parse_func_def_exp()?;
+        if is_method {
+            func_def.par_list.insert(0, "self".to_string());
+        }
+        Ok(Box::new(AssignStat {
+            last_line: func_def.line,
+            var_list: vec![var],
+            exp_list: vec![func_def],
+        }))
+    }
+    /// `function Name {'.' Name} [':' Name]`, returning the variable and
+    /// whether it names a method.
+    fn parse_func_name(&mut self) -> ParseResult<(Box<dyn Exp>, bool)> {
+        self.expected_key_word(KeyWord::FUN)?;
+        let name_token = self.expected_id()?;
+        let mut var: Box<dyn Exp> = Box::new(IDExp {
+            line: name_token.line,
+            name: name_token.raw_data,
+        });
+        let mut is_method = false;
+        while !is_method && (self.peek_key_word(KeyWord::DOT)? || self.peek_key_word(KeyWord::COL)?) {
+            is_method = self.peek_key_word(KeyWord::COL)?;
+            self.skip_token()?;
+            let key = self.expected_name_exp()?;
+            var = Box::new(TableAccessExp { last_line: key.line, prefix: var, key });
+        }
+        Ok((var, is_method))
     }
-    fn parse_range_for_stat(&mut self, first_val: String) -> Box<dyn Stat> {
-        let name_list = self.parse_name_list();
-        self.expected_token(tk_from_kw!(KeyWord::ASS));
-        let exp_list = self.parse_exp_list();
-        self.expected_token(tk_from_kw!(KeyWord::DO));
-        let block = self.parse();
-        self.expected_token(tk_from_kw!(KeyWord::END));
-        Box::new(RangeForStat { block_beg_line: 0, name_list, exp_list, block })
-    }
-    fn parse_func_def_stat(&mut self) -> FuncDefExp {}
-    fn parse_local_stat(&mut self) -> Box<dyn Stat> {
-        self.expected_token(tk_from_kw!(KeyWord::LOC));
-        if self.lexer.peek_token_type().eq(&tk_from_kw!(KeyWord::FUN)) {
-            self.parse_local_func_def_stat()
+    fn parse_local_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        self.expected_key_word(KeyWord::LOC)?;
+        if self.peek_key_word(KeyWord::FUN)? {
+            self.parse_local_func_stat()
         } else {
             self.parse_local_val_stat()
         }
     }
-    fn parse_local_func_stat(&mut self) -> Box<dyn Stat> {
-        self.expected_token(tk_from_kw!(KeyWord::FUN));
-        let name = self.expected_id().raw_data;
-        let func_body = self.parse_func_def_stat();
-        Box::new(LocalFuncDefStat { name, exp: func_body })
+    fn parse_local_func_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        self.expected_key_word(KeyWord::FUN)?;
+        let name = self.expected_id()?.raw_data;
+        let func_body = self.parse_func_def_exp()?;
+        Ok(Box::new(LocalFuncDefStat { name, exp: *func_body }))
     }
-    fn parse_local_val_stat(&mut self) -> Box<dyn Stat> {
-        let name_list = self.parse_name_list();
+    fn parse_local_val_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        let name_list = self.parse_name_list()?;
         let mut exp_list = Vec::new();
-        if self.lexer.peek_token_type().eq(&tk_from_kw!(KeyWord::ASS)) {
-            self.lexer.next_token();
-            exps = self.parse_exp_list();
+        if self.peek_key_word(KeyWord::ASS)? {
+            self.skip_token()?;
+            exp_list = self.parse_exp_list()?;
+        }
+        let last_line = self.lexer.last_line();
+        Ok(Box::new(LocalVarDefStat { last_line, name_list, exp_list }))
+    }
+    fn parse_func_call_or_assign_stat(&mut self) -> ParseResult<Box<dyn Stat>> {
+        let prefix_exp = self.parse_suffixed_exp()?;
+        match prefix_exp {
+            _ if self.peek_key_word(KeyWord::ASS)? || self.peek_key_word(KeyWord::COM)? => {
+                self.parse_assign_stat(prefix_exp)
+            }
+            PrefixExp::Call(call_exp) => self.parse_func_call_stat(call_exp),
+            _ => self.error_near("syntax error")
+        }
+    }
+    fn parse_func_call_stat(&mut self, call_exp: Box<FuncCallStat>) -> ParseResult<Box<dyn Stat>> {
+        Ok(call_exp)
+    }
+    fn parse_assign_stat(&mut self, first_var: PrefixExp) -> ParseResult<Box<dyn Stat>> {
+        let mut var_list = vec![self.check_var(first_var)?];
+        while self.peek_key_word(KeyWord::COM)? {
+            self.skip_token()?;
+            let var = self.parse_suffixed_exp()?;
+            var_list.push(self.check_var(var)?);
+        }
+        self.expected_key_word(KeyWord::ASS)?;
+        let exp_list = self.parse_exp_list()?;
+        let last_line = self.lexer.last_line();
+        Ok(Box::new(AssignStat { last_line, var_list, exp_list }))
+    }
+
+    fn check_var(&mut self, exp: PrefixExp) -> ParseResult<Box<dyn Exp>> {
+        match exp {
+            PrefixExp::Name(_) | PrefixExp::Access(_) => Ok(exp.into_exp()),
+            _ => self.error_near("syntax error")
         }
-        Box::new(LocalVarDefStat { last_line: 0, name_list, exp_list })
     }
-    fn parse_func_call_or_assign_stat(&mut self) -> Box<dyn Stat> {}
-    fn parse_func_call_stat(&mut self) -> Box<dyn Stat> {}
-    fn parse_assign_stat(&mut self) -> Box<dyn Stat> {}
 }
 
 impl Parser {
-    fn parse_exp_list(&mut self) -> Vec<Box<dyn Exp>> {
-        let mut exps = vec![self.parse_exp()];
-        while self.lexer.peek_token_type().eq(&tk_from_kw!(KeyWord::COM)) {
-            self.lexer.next_token();
-            exps.push(self.parse_exp());
+    fn parse_exp_list(&mut self) -> ParseResult<Vec<Box<dyn Exp>>> {
+        let mut exps = vec![self.parse_exp()?];
+        while self.peek_key_word(KeyWord::COM)? {
+            self.skip_token()?;
+            exps.push(self.parse_exp()?);
+        }
+        Ok(exps)
+    }
+
+    fn parse_name_list(&mut self) -> ParseResult<Vec<String>> {
+        let mut names = vec![self.expected_id()?.raw_data];
+        while self.peek_key_word(KeyWord::COM)? {
+            self.skip_token()?;
+            names.push(self.expected_id()?.raw_data);
         }
-        exps
+        Ok(names)
     }
 
-    fn parse_name_list(&mut self) -> Vec<String> {}
+    fn parse_args(&mut self) -> ParseResult<Vec<Box<dyn Exp>>> {
+        match self.peek_op()? {
+            Some(KeyWord::LLA) => Ok(vec![self.parse_table_cons_exp()?]),
+            Some(KeyWord::LSM) => self.parse_paren_args(),
+            _ => self.parse_string_args()
+        }
+    }
+
+    fn parse_paren_args(&mut self) -> ParseResult<Vec<Box<dyn Exp>>> {
+        let line = self.next_line()?;
+        let mut args = Vec::new();
+        if !self.peek_key_word(KeyWord::RSM)? {
+            args = self.parse_exp_list()?;
+        }
+        self.expected_match(KeyWord::RSM, KeyWord::LSM, line)?;
+        Ok(args)
+    }
+
+    fn parse_string_args(&mut self) -> ParseResult<Vec<Box<dyn Exp>>> {
+        match self.lexer.peek_token_type()? {
+            TokenType::String(str) => {
+                let line = self.next_line()?;
+                Ok(vec![Box::new(StringExp { line, str })])
+            }
+            _ => self.error_near("function arguments expected")
+        }
+    }
 }
 
 impl Parser {
-    fn parse_exp(&mut self) -> Box<dyn Exp> {}
+    /// Left and right priority of a binary operator, as in the reference
+    /// implementation; a right priority lower than the left one makes the
+    /// operator right associative.
+    fn binary_priority(key_word: &KeyWord) -> Option<(usize, usize)> {
+        Some(match key_word {
+            KeyWord::OR => (1, 1),
+            KeyWord::AND => (2, 2),
+            KeyWord::LE | KeyWord::GR | KeyWord::LEE |
+            KeyWord::GRE | KeyWord::NEQ | KeyWord::EQU => (3, 3),
+            KeyWord::BOR => (4, 4),
+            KeyWord::BXOR => (5, 5),
+            KeyWord::BAND => (6, 6),
+            KeyWord::LSH | KeyWord::RSH => (7, 7),
+            // `..` is right associative, but its chains become one ConExp
+            KeyWord::CON => (9, 9),
+            KeyWord::ADD | KeyWord::SUB => (10, 10),
+            KeyWord::MUL | KeyWord::DIV | KeyWord::FDIV | KeyWord::MOD => (11, 11),
+            KeyWord::POW => (14, 13),
+            _ => return None
+        })
+    }
+    const UNARY_PRIORITY: usize = 12;
+
+    /// Returns the binary operator ahead and its right priority if it binds
+    /// tighter than `limit`.
+    fn peek_binary_operator(&mut self, limit: usize) -> ParseResult<Option<(KeyWord, usize)>> {
+        let op = match self.peek_op()? {
+            Some(op) => op,
+            None => return Ok(None)
+        };
+        Ok(match Parser::binary_priority(&op) {
+            Some((left, right)) if left > limit => Some((op, right)),
+            _ => None
+        })
+    }
+
+    fn parse_exp(&mut self) -> ParseResult<Box<dyn Exp>> {
+        self.parse_sub_exp(0)
+    }
+
+    /// Parses an expression whose binary operators all bind tighter than
+    /// `limit`.
+    fn parse_sub_exp(&mut self, limit: usize) -> ParseResult<Box<dyn Exp>> {
+        self.enter_level()?;
+        let mut exp = match self.peek_op()? {
+            Some(op) if op.is_unique_operator() => self.parse_unop_exp(op)?,
+            _ => self.parse_simple_exp()?
+        };
+        while let Some((op, right_priority)) = self.peek_binary_operator(limit)? {
+            exp = self.parse_binop_exp(exp, op, right_priority)?;
+        }
+        self.leave_level();
+        Ok(exp)
+    }
+
+    fn parse_unop_exp(&mut self, op: KeyWord) -> ParseResult<Box<dyn Exp>> {
+        let line = self.next_line()?;
+        let op = match op {
+            KeyWord::SUB => KeyWord::MIN,
+            _ => op
+        };
+        let exp = self.parse_sub_exp(Parser::UNARY_PRIORITY)?;
+        Ok(Box::new(UnopExp { line, op, exp }))
+    }
+
+    /// Parses the right operand of `op`; chains of `..` are collected into
+    /// one `ConExp`.
+    fn parse_binop_exp(&mut self, left_exp: Box<dyn Exp>, op: KeyWord, right_priority: usize)
+                       -> ParseResult<Box<dyn Exp>> {
+        let mut line = self.next_line()?;
+        if op != KeyWord::CON {
+            let right_exp = self.parse_sub_exp(right_priority)?;
+            return Ok(Box::new(BinopExp { line, op, left_exp, right_exp }));
+        }
+        let mut exps = vec![left_exp, self.parse_sub_exp(right_priority)?];
+        while self.peek_key_word(KeyWord::CON)? {
+            line = self.next_line()?;
+            exps.push(self.parse_sub_exp(right_priority)?);
+        }
+        Ok(Box::new(ConExp { line, exps }))
+    }
 
-    fn parse_prefix_exp(&mut self) -> Box<dyn Exp> {}
+    fn parse_simple_exp(&mut self) -> ParseResult<Box<dyn Exp>> {
+        match self.parse_literal_exp()? {
+            Some(exp) => Ok(exp),
+            None => match self.peek_op()? {
+                Some(KeyWord::LLA) => self.parse_table_cons_exp(),
+                Some(KeyWord::FUN) => self.parse_func_exp(),
+                _ => self.parse_prefix_exp()
+            }
+        }
+    }
+
+    fn parse_func_exp(&mut self) -> ParseResult<Box<dyn Exp>> {
+        self.expected_key_word(KeyWord::FUN)?;
+        Ok(self.parse_func_def_exp()?)
+    }
+
+    /// Parses `nil`, `true`, `false`, `...`, a number or a string if one is
+    /// next.
+    fn parse_literal_exp(&mut self) -> ParseResult<Option<Box<dyn Exp>>> {
+        let token = self.lexer.peek_token()?;
+        let line = token.line;
+        let exp: Box<dyn Exp> = match &token.type_id {
+            TokenType::OptKeyWord(KeyWord::VAR) => Box::new(VarargExp { line }),
+            TokenType::OptKeyWord(KeyWord::NIL) => Box::new(NilExp { line }),
+            TokenType::OptKeyWord(KeyWord::TRU) => Box::new(TrueExp { line }),
+            TokenType::OptKeyWord(KeyWord::FAL) => Box::new(FalseExp { line }),
+            TokenType::Number(num) => match str_to_integer(&token.raw_data) {
+                Some(num) => Box::new(IntegerExp { line, num }),
+                None => Box::new(FloatExp { line, num: *num })
+            },
+            TokenType::String(str) => Box::new(StringExp { line, str: str.clone() }),
+            _ => return Ok(None)
+        };
+        self.skip_token()?;
+        Ok(Some(exp))
+    }
+
+    fn parse_table_cons_exp(&mut self) -> ParseResult<Box<dyn Exp>> {
+        let line = self.expected_key_word(KeyWord::LLA)?;
+        let mut key_exps = Vec::new();
+        let mut val_exps = Vec::new();
+        while !self.peek_key_word(KeyWord::RLA)? {
+            self.parse_field(&mut key_exps, &mut val_exps)?;
+            if self.peek_key_word(KeyWord::COM)? || self.peek_key_word(KeyWord::SEM)? {
+                self.skip_token()?;
+            } else {
+                break;
+            }
+        }
+        let last_line = self.peek_line()?;
+        self.expected_match(KeyWord::RLA, KeyWord::LLA, line)?;
+        Ok(Box::new(TableConsExp { line, last_line, key_exps, val_exps }))
+    }
+
+    /// One field of a table constructor: `[exp] = exp`, `Name = exp` or `exp`.
+    fn parse_field(&mut self, key_exps: &mut Vec<Option<Box<dyn Exp>>>, val_exps: &mut Vec<Box<dyn Exp>>) -> ParseResult<()> {
+        let key_exp: Option<Box<dyn Exp>> = if self.peek_key_word(KeyWord::LMI)? {
+            self.skip_token()?;
+            let key_exp = self.parse_exp()?;
+            self.expected_key_word(KeyWord::RMI)?;
+            self.expected_key_word(KeyWord::ASS)?;
+            Some(key_exp)
+        } else if self.lexer.peek_token()?.get_id().is_some()
+            && self.lexer.lookahead_token_type()?.eq(&tk_from_kw!(KeyWord::ASS)) {
+            let key_exp = self.expected_name_exp()?;
+            self.skip_token()?;
+            Some(key_exp)
+        } else {
+            None
+        };
+        key_exps.push(key_exp);
+        val_exps.push(self.parse_exp()?);
+        Ok(())
+    }
+
+    fn parse_func_def_exp(&mut self) -> ParseResult<Box<FuncDefExp>> {
+        let line = self.expected_key_word(KeyWord::LSM)?;
+        let (par_list, is_vararg) = self.parse_par_list()?;
+        let block = self.parse()?;
+        let last_line = self.peek_line()?;
+        self.expected_match(KeyWord::END, KeyWord::FUN, line)?;
+        Ok(Box::new(FuncDefExp { line, last_line, par_list, is_vararg, block }))
+    }
+
+    /// Parameter names up to and including the closing `)`, and whether the
+    /// list ends with `...`.
+    fn parse_par_list(&mut self) -> ParseResult<(Vec<String>, bool)> {
+        let mut par_list = Vec::new();
+        let mut is_vararg = false;
+        while !self.peek_key_word(KeyWord::RSM)? {
+            if self.peek_key_word(KeyWord::VAR)? {
+                self.skip_token()?;
+                is_vararg = true;
+                break;
+            }
+            par_list.push(self.expected_id()?.raw_data);
+            if !self.peek_key_word(KeyWord::COM)? {
+                break;
+            }
+            self.skip_token()?;
+        }
+        self.expected_key_word(KeyWord::RSM)?;
+        Ok((par_list, is_vararg))
+    }
+
+    fn parse_prefix_exp(&mut self) -> ParseResult<Box<dyn Exp>> {
+        Ok(self.parse_suffixed_exp()?.into_exp())
+    }
+
+    fn parse_primary_exp(&mut self) -> ParseResult<PrefixExp> {
+        if !self.peek_key_word(KeyWord::LSM)? {
+            return self.parse_name_exp();
+        }
+        let line = self.next_line()?;
+        let in_exp = self.parse_exp()?;
+        self.expected_match(KeyWord::RSM, KeyWord::LSM, line)?;
+        Ok(PrefixExp::Parens(Box::new(ParensExp { in_exp })))
+    }
+
+    fn parse_name_exp(&mut self) -> ParseResult<PrefixExp> {
+        match self.lexer.peek_token_type()? {
+            TokenType::ID(_) => {
+                let token = self.lexer.next_token()?;
+                Ok(PrefixExp::Name(Box::new(IDExp { line: token.line, name: token.raw_data })))
+            }
+            _ => self.error_near("unexpected symbol")
+        }
+    }
+
+    fn is_suffix_ahead(&mut self) -> ParseResult<bool> {
+        Ok(match self.lexer.peek_token()?.type_id {
+            TokenType::OptKeyWord(ref key_word) => matches!(key_word,
+                KeyWord::DOT | KeyWord::LMI | KeyWord::COL | KeyWord::LSM | KeyWord::LLA),
+            TokenType::String(_) => true,
+            _ => false
+        })
+    }
+
+    fn parse_suffixed_exp(&mut self) -> ParseResult<PrefixExp> {
+        let mut exp = self.parse_primary_exp()?;
+        while self.is_suffix_ahead()? {
+            exp = self.parse_suffix(exp.into_exp())?;
+        }
+        Ok(exp)
+    }
+
+    /// Parses one `.Name`, `[exp]`, `:Name args` or `args` after `prefix`.
+    fn parse_suffix(&mut self, prefix: Box<dyn Exp>) -> ParseResult<PrefixExp> {
+        match self.peek_op()? {
+            Some(KeyWord::DOT) => {
+                self.skip_token()?;
+                let key = self.expected_name_exp()?;
+                Ok(PrefixExp::Access(Box::new(TableAccessExp { last_line: key.line, prefix, key })))
+            }
+            Some(KeyWord::LMI) => self.parse_index_suffix(prefix),
+            _ => self.parse_call_suffix(prefix)
+        }
+    }
+
+    fn parse_index_suffix(&mut self, prefix: Box<dyn Exp>) -> ParseResult<PrefixExp> {
+        self.expected_key_word(KeyWord::LMI)?;
+        let key = self.parse_exp()?;
+        let last_line = self.expected_key_word(KeyWord::RMI)?;
+        Ok(PrefixExp::Access(Box::new(TableAccessExp { last_line, prefix, key })))
+    }
+
+    fn parse_call_suffix(&mut self, prefix: Box<dyn Exp>) -> ParseResult<PrefixExp> {
+        let line = self.peek_line()?;
+        let name_exp = self.parse_method_name()?;
+        let args = self.parse_args()?;
+        let last_line = self.lexer.last_line();
+        Ok(PrefixExp::Call(Box::new(FuncCallExp { line, last_line, prefix, name_exp, args })))
+    }
+
+    /// The `:Name` of a method call, if there is one.
+    fn parse_method_name(&mut self) -> ParseResult<Option<StringExp>> {
+        if !self.peek_key_word(KeyWord::COL)? {
+            return Ok(None);
+        }
+        self.skip_token()?;
+        Ok(Some(*self.expected_name_exp()?))
+    }
 }
 
 impl Parser {
-    fn parse_stats(&mut self) -> Vec<Box<dyn Stat>> {
+    fn parse_stats(&mut self) -> ParseResult<Vec<Box<dyn Stat>>> {
         let mut stats = Vec::new();
-        while !Parser::is_ret_or_block_end(&self.lexer.peek_token_type()) {
-            let stat = self.parse_stat();
+        while !Parser::is_ret_or_block_end(&self.lexer.peek_token_type()?) {
+            let stat = self.parse_stat()?;
             if let StatType::EmptyStatTag = stat.get_type() {
                 continue;
             }
             stats.push(stat);
         }
-        stats
+        Ok(stats)
     }
 
-    fn parse_ret_exps(&mut self) -> Option<Vec<Box<dyn Exp>>> {
+    fn parse_ret_exps(&mut self) -> ParseResult<Option<Vec<Box<dyn Exp>>>> {
+        if !self.peek_key_word(KeyWord::RET)? {
+            return Ok(None);
+        }
+        self.skip_token()?;
         let mut exps = Vec::new();
-        if !self.lexer.peek_token_type().eq(tk_from_kw!(KeyWord::RET)) {
-            return None;
+        if !self.is_ret_exps_end()? {
+            exps = self.parse_exp_list()?;
         }
-        self.lexer.next_token();
-        Some(match self.lexer.peek_token_type() {
-            TokenType::OptKeyWord(key_word) => {
-                match key_word {
-                    KeyWord::END |
-                    KeyWord::ELI |
-                    KeyWord::ELS |
-                    KeyWord::UNT => exps,
-                    KeyWord::SEM => {
-                        self.lexer.next_token();
-                        exps
-                    }
-                    _ => panic!("grammar error!")
-                }
-            }
-            TokenType::EOF => exps,
-            _ => {
-                exps = self.parse_exp_list();
-                if self.lexer.peek_token_type().eq(tk_from_kw!(KeyWord::SEM)) {
-                    self.lexer.next_token();
-                }
-                exps
-            }
-        })
+        if self.peek_key_word(KeyWord::SEM)? {
+            self.skip_token()?;
+        }
+        Ok(Some(exps))
     }
 
-    pub fn parse(&mut self) -> Block {
-        let stats = self.parse_stats();
-        let opt_ret_exps = self.parse_ret_exps();
-        Block::new(0, stats, opt_ret_exps)
+    /// Whether a `return` has no expressions: the block ends or a `;` follows.
+    fn is_ret_exps_end(&mut self) -> ParseResult<bool> {
+        Ok(matches!(self.lexer.peek_token_type()?,
+            TokenType::OptKeyWord(KeyWord::END) |
+            TokenType::OptKeyWord(KeyWord::ELI) |
+            TokenType::OptKeyWord(KeyWord::ELS) |
+            TokenType::OptKeyWord(KeyWord::UNT) |
+            TokenType::OptKeyWord(KeyWord::SEM) |
+            TokenType::EOF))
+    }
+
+    pub fn parse(&mut self) -> ParseResult<Block> {
+        let stats = self.parse_stats()?;
+        let opt_ret_exps = self.parse_ret_exps()?;
+        Ok(Block::new(self.peek_line()?, stats, opt_ret_exps))
+    }
+
+    /// Parses a whole chunk, which unlike a nested block must run to the end
+    /// of the source.
+    pub fn parse_chunk(&mut self) -> Result<Block, SyntaxError> {
+        let block = self.parse().map_err(|err| *err)?;
+        self.expected_token(TokenType::EOF).map_err(|err| *err)?;
+        Ok(block)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(code: &str) -> Result<Block, SyntaxError> {
+        Parser::new(code, "test").parse_chunk()
+    }
+
+    fn error(code: &str) -> String {
+        parse(code).unwrap_err().to_string()
+    }
+
+    /// Debug form of the expression in `return <exp>` without line numbers,
+    /// which keeps the expected trees short.
+    fn exp_tree(exp: &str) -> String {
+        let block = parse(&format!("return {}", exp)).unwrap();
+        let mut tree = format!("{:?}", block.ret_exps.unwrap()[0]);
+        for field in ["last_line: ", "line: "] {
+            while let Some(pos) = tree.find(field) {
+                let end = tree[pos + field.len()..].find(", ").unwrap() + pos + field.len() + 2;
+                tree.replace_range(pos..end, "");
+            }
+        }
+        tree
+    }
+
+    #[test]
+    fn unary_minus_binds_looser_than_pow() {
+        assert_eq!(exp_tree("-2^2"),
+                   "UnopExp { op: MIN, exp: BinopExp { op: POW, \
+                   left_exp: IntegerExp { num: 2 }, right_exp: IntegerExp { num: 2 } } }");
+        assert_eq!(exp_tree("2^-3"),
+                   "BinopExp { op: POW, left_exp: IntegerExp { num: 2 }, \
+                   right_exp: UnopExp { op: MIN, exp: IntegerExp { num: 3 } } }");
+    }
+
+    #[test]
+    fn pow_is_right_associative() {
+        assert_eq!(exp_tree("2^3^2"),
+                   "BinopExp { op: POW, left_exp: IntegerExp { num: 2 }, \
+                   right_exp: BinopExp { op: POW, left_exp: IntegerExp { num: 3 }, right_exp: IntegerExp { num: 2 } } }");
+    }
+
+    #[test]
+    fn additive_operators_are_left_associative() {
+        assert_eq!(exp_tree("1-2+3"),
+                   "BinopExp { op: ADD, left_exp: BinopExp { op: SUB, \
+                   left_exp: IntegerExp { num: 1 }, right_exp: IntegerExp { num: 2 } }, right_exp: IntegerExp { num: 3 } }");
+        assert_eq!(exp_tree("1+2*3"),
+                   "BinopExp { op: ADD, left_exp: IntegerExp { num: 1 }, \
+                   right_exp: BinopExp { op: MUL, left_exp: IntegerExp { num: 2 }, right_exp: IntegerExp { num: 3 } } }");
+    }
+
+    #[test]
+    fn concatenation_collects_operands() {
+        assert_eq!(exp_tree("a .. b .. 1 + 2"),
+                   "ConExp { exps: [IDExp { name: \"a\" }, IDExp { name: \"b\" }, \
+                   BinopExp { op: ADD, left_exp: IntegerExp { num: 1 }, right_exp: IntegerExp { num: 2 } }] }");
+        assert_eq!(exp_tree("a .. b << 1"),
+                   "BinopExp { op: LSH, left_exp: ConExp { exps: [IDExp { name: \"a\" }, IDExp { name: \"b\" }] }, \
+                   right_exp: IntegerExp { num: 1 } }");
+    }
+
+    #[test]
+    fn bitwise_operator_levels() {
+        // | is looser than ~, which is looser than &, which is looser than shifts
+        assert_eq!(exp_tree("1 | 2 ~ 3 & 4 << 5"),
+                   "BinopExp { op: BOR, left_exp: IntegerExp { num: 1 }, \
+                   right_exp: BinopExp { op: BXOR, left_exp: IntegerExp { num: 2 }, \
+                   right_exp: BinopExp { op: BAND, left_exp: IntegerExp { num: 3 }, \
+                   right_exp: BinopExp { op: LSH, left_exp: IntegerExp { num: 4 }, right_exp: IntegerExp { num: 5 } } } } }");
+        assert_eq!(exp_tree("1 == 2 | 3"),
+                   "BinopExp { op: EQU, left_exp: IntegerExp { num: 1 }, \
+                   right_exp: BinopExp { op: BOR, left_exp: IntegerExp { num: 2 }, right_exp: IntegerExp { num: 3 } } }");
+        assert_eq!(exp_tree("~1"), "UnopExp { op: BXOR, exp: IntegerExp { num: 1 } }");
+    }
+
+    #[test]
+    fn logical_operator_levels() {
+        assert_eq!(exp_tree("a or b and not c"),
+                   "BinopExp { op: OR, left_exp: IDExp { name: \"a\" }, \
+                   right_exp: BinopExp { op: AND, left_exp: IDExp { name: \"b\" }, \
+                   right_exp: UnopExp { op: NOT, exp: IDExp { name: \"c\" } } } }");
+    }
+
+    #[test]
+    fn number_literals() {
+        assert_eq!(exp_tree("0xff"), "IntegerExp { num: 255 }");
+        assert_eq!(exp_tree("1.5"), "FloatExp { num: 1.5 }");
+        assert_eq!(exp_tree("9223372036854775808"), "FloatExp { num: 9.223372036854776e18 }");
+    }
+
+    #[test]
+    fn table_constructor_fields() {
+        assert_eq!(exp_tree("{1, x = 2, [3] = 4; 5,}"),
+                   "TableConsExp { key_exps: [None, Some(StringExp { str: \"x\" }), Some(IntegerExp { num: 3 }), None], \
+                   val_exps: [IntegerExp { num: 1 }, IntegerExp { num: 2 }, IntegerExp { num: 4 }, IntegerExp { num: 5 }] }");
+        assert_eq!(exp_tree("{x == 1}"),
+                   "TableConsExp { key_exps: [None], \
+                   val_exps: [BinopExp { op: EQU, left_exp: IDExp { name: \"x\" }, right_exp: IntegerExp { num: 1 } }] }");
+        assert_eq!(exp_tree("{}"), "TableConsExp { key_exps: [], val_exps: [] }");
+        assert_eq!(error("t = {1 2}"), "test:1: '}' expected near '2'");
+    }
+
+    #[test]
+    fn method_definition_inserts_self() {
+        let block = parse("function a.b:c(x, ...) end").unwrap();
+        let tree = format!("{:?}", block.stats[0]);
+        assert!(tree.contains("par_list: [\"self\", \"x\"], is_vararg: true"), "{}", tree);
+        assert!(tree.contains("prefix: TableAccessExp { last_line: 1, prefix: IDExp { line: 1, name: \"a\" }"), "{}", tree);
+        assert!(tree.contains("key: StringExp { line: 1, str: \"c\" }"), "{}", tree);
+
+        let block = parse("function a.b(x) end").unwrap();
+        assert!(format!("{:?}", block.stats[0]).contains("par_list: [\"x\"]"));
+    }
+
+    #[test]
+    fn call_statements_and_assignments() {
+        let block = parse("f() a.b = 1 c:d 'x' e {}").unwrap();
+        let types: Vec<String> = block.stats.iter()
+            .map(|stat| format!("{:?}", stat).split(' ').next().unwrap().to_string())
+            .collect();
+        assert_eq!(types, ["FuncCallExp", "AssignStat", "FuncCallExp", "FuncCallExp"]);
+        assert_eq!(error("f() = 1"), "test:1: syntax error near '='");
+        assert_eq!(error("x"), "test:1: syntax error near <eof>");
+    }
+
+    #[test]
+    fn unfinished_constructs() {
+        assert_eq!(error("if x then"), "test:1: 'end' expected near <eof>");
+        assert_eq!(error("while x do\n"), "test:2: 'end' expected (to close 'while' at line 1) near <eof>");
+        assert_eq!(error("repeat\nx = 1"), "test:2: 'until' expected (to close 'repeat' at line 1) near <eof>");
+        assert_eq!(error("x = (1"), "test:1: ')' expected near <eof>");
+        assert_eq!(error("x = {\n1"), "test:2: '}' expected (to close '{' at line 1) near <eof>");
+        assert_eq!(error("x ="), "test:1: unexpected symbol near <eof>");
+        assert_eq!(error("local"), "test:1: <name> expected near <eof>");
+        assert_eq!(error("for i = 1 do end"), "test:1: ',' expected near 'do'");
+    }
+
+    #[test]
+    fn return_must_end_block() {
+        assert!(parse("do return end").is_ok());
+        assert!(parse("return 1;").is_ok());
+        assert_eq!(error("return 1 x = 2"), "test:1: '<eof>' expected near 'x'");
+    }
+
+    #[test]
+    fn lexical_errors_are_returned() {
+        assert_eq!(error("x = 1 €"), "test:1: unexpected symbol near '€'");
+        assert_eq!(error("x = \"abc"), "test:1: unfinished string near '\"abc'");
+    }
+
+    /// `open` repeated `n` times around `mid`, followed by `close` repeated.
+    fn nested(open: &str, mid: &str, close: &str, n: usize) -> String {
+        format!("{}{}{}", open.repeat(n), mid, close.repeat(n))
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let cases = [
+            ("x = ", "(", "1", ")", "'('"),
+            ("x = ", "{", "1", "}", "'{'"),
+            ("x = ", "{[", "1", "]=1}", "'{'"),
+            ("x = ", "a[", "1", "]", "'a'"),
+            ("x = ", "f(", "1", ")", "'f'"),
+            ("x = ", "a:b(", "1", ")", "'a'"),
+            ("x = ", "- ", "1", "", "'-'"),
+            ("x = ", "1^", "1", "", "'1'"),
+            ("x = ", "function() return ", "1", " end", "'function'"),
+            ("", "do ", "", " end", "'do'"),
+            ("", "if x then ", "", " end", "'x'"),
+            ("", "for k in x do ", "", " end", "'x'"),
+            ("", "f(function() ", "", " end)", "'f'"),
+        ];
+        for (prefix, open, mid, close, near) in cases.iter() {
+            let code = format!("{}{}", prefix, nested(open, mid, close, 1000));
+            let msg = error(&code);
+            assert!(msg.starts_with("test:1: chunk has too many syntax levels near "), "{}: {}", open, msg);
+            assert!(msg.ends_with(near), "{}: {}", open, msg);
+        }
+        assert!(parse(&format!("x = {}", nested("(", "1", ")", 150))).is_ok());
+        assert!(parse(&format!("x = {}", nested("f(", "1", ")", 150))).is_ok());
+    }
+}
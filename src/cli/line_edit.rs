@@ -0,0 +1,373 @@
+use std::io::{self, Read, Write, IsTerminal};
+use std::process::{Command, Stdio};
+
+const CTRL_A: u8 = 0x01;
+const CTRL_B: u8 = 0x02;
+const CTRL_C: u8 = 0x03;
+const CTRL_D: u8 = 0x04;
+const CTRL_E: u8 = 0x05;
+const CTRL_F: u8 = 0x06;
+const CTRL_K: u8 = 0x0b;
+const CTRL_N: u8 = 0x0e;
+const CTRL_P: u8 = 0x10;
+const CTRL_U: u8 = 0x15;
+const BACKSPACE: u8 = 0x08;
+const ESC: u8 = 0x1b;
+const DEL: u8 = 0x7f;
+
+pub enum ReadResult {
+    Line(String),
+    Interrupted,
+    Eof,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Up,
+    Down,
+    Home,
+    End,
+    KillToEnd,
+    KillToStart,
+    Interrupt,
+    EofOrDelete,
+    Ignored,
+}
+
+/// Puts the terminal into non-canonical, no-echo mode for as long as it
+/// lives. `stty` is used so that no libc bindings are needed.
+struct RawMode {
+    saved_state: String,
+}
+
+impl RawMode {
+    fn enable() -> Option<RawMode> {
+        let output = Command::new("stty").arg("-g")
+            .stdin(Stdio::inherit()).stderr(Stdio::null()).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let saved_state = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        let status = Command::new("stty").args(["-icanon", "-echo", "-isig", "min", "1"])
+            .stdin(Stdio::inherit()).stderr(Stdio::null()).status().ok()?;
+        match status.success() {
+            true => Some(RawMode { saved_state }),
+            false => None
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = Command::new("stty").arg(&self.saved_state)
+            .stdin(Stdio::inherit()).stderr(Stdio::null()).status();
+    }
+}
+
+/// Minimal line editor: cursor and word movement, deletion, kill commands
+/// and history browsing with the usual emacs-style keys and arrow keys.
+pub struct LineEditor {
+    history: Vec<String>,
+    interactive: bool,
+}
+
+impl LineEditor {
+    pub fn new(history: Vec<String>) -> LineEditor {
+        LineEditor { history, interactive: false }
+    }
+
+    pub fn history(&self) -> &Vec<String> {
+        &self.history
+    }
+
+    /// Whether lines are edited, i.e. stdin is a terminal that could be put
+    /// into raw mode. Piped input is not kept in the history.
+    pub fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+
+    pub fn add_history(&mut self, line: &str) {
+        if !self.interactive || line.trim().is_empty() || self.history.last().map(|last| last.as_str()) == Some(line) {
+            return;
+        }
+        self.history.push(line.to_string());
+    }
+
+    pub fn read_line(&mut self, prompt: &str) -> ReadResult {
+        if io::stdin().is_terminal() {
+            if let Some(raw_mode) = RawMode::enable() {
+                self.interactive = true;
+                let result = self.edit_line(prompt);
+                drop(raw_mode);
+                return result;
+            }
+        }
+        self.read_plain_line(prompt)
+    }
+
+    fn read_plain_line(&mut self, prompt: &str) -> ReadResult {
+        print!("{}", prompt);
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => ReadResult::Eof,
+            Ok(_) => {
+                let len = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(len);
+                ReadResult::Line(line)
+            }
+        }
+    }
+
+    fn read_byte() -> Option<u8> {
+        let mut buf = [0u8; 1];
+        match io::stdin().lock().read(&mut buf) {
+            Ok(1) => Some(buf[0]),
+            _ => None
+        }
+    }
+
+    /// Reads the rest of a UTF-8 sequence. Invalid input is ignored; `None`
+    /// means that reading failed.
+    fn read_utf8_char<I: Iterator<Item = u8>>(first: u8, bytes: &mut I) -> Option<Key> {
+        let len = match first {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            // a stray continuation byte
+            _ => return Some(Key::Ignored),
+        };
+        let mut buf = vec![first];
+        for _ in 1..len {
+            buf.push(bytes.next()?);
+        }
+        Some(match std::str::from_utf8(&buf) {
+            Ok(str) => Key::Char(str.chars().next().unwrap()),
+            Err(_) => Key::Ignored
+        })
+    }
+
+    fn read_escape<I: Iterator<Item = u8>>(bytes: &mut I) -> Option<Key> {
+        let kind = bytes.next()?;
+        let (params, final_byte) = match kind {
+            b'b' => return Some(Key::WordLeft),
+            b'f' => return Some(Key::WordRight),
+            b'O' => (Vec::new(), bytes.next()?),
+            b'[' => {
+                // a CSI sequence is parameter and intermediate bytes ended by
+                // a final byte in 0x40..=0x7e, such as ESC [ 3 ~ or ESC [ 1 ; 5 C
+                let mut params = Vec::new();
+                loop {
+                    match bytes.next()? {
+                        byte @ 0x40..=0x7e => break (params, byte),
+                        byte @ 0x20..=0x3f => params.push(byte),
+                        _ => return Some(Key::Ignored),
+                    }
+                }
+            }
+            _ => return Some(Key::Ignored),
+        };
+        Some(match (params.as_slice(), final_byte) {
+            (b"" | b"1", b'A') => Key::Up,
+            (b"" | b"1", b'B') => Key::Down,
+            (b"" | b"1", b'C') => Key::Right,
+            (b"" | b"1", b'D') => Key::Left,
+            (b"" | b"1", b'H') => Key::Home,
+            (b"" | b"1", b'F') => Key::End,
+            // Ctrl or Alt with an arrow key
+            (b"1;5" | b"1;3", b'C') => Key::WordRight,
+            (b"1;5" | b"1;3", b'D') => Key::WordLeft,
+            (b"1" | b"7", b'~') => Key::Home,
+            (b"4" | b"8", b'~') => Key::End,
+            (b"3", b'~') => Key::Delete,
+            _ => Key::Ignored
+        })
+    }
+
+    /// Decodes the next key from the bytes typed at the terminal. `None`
+    /// means that the input ended.
+    fn read_key<I: Iterator<Item = u8>>(bytes: &mut I) -> Option<Key> {
+        let byte = bytes.next()?;
+        Some(match byte {
+            b'\r' | b'\n' => Key::Enter,
+            BACKSPACE | DEL => Key::Backspace,
+            CTRL_A => Key::Home,
+            CTRL_B => Key::Left,
+            CTRL_C => Key::Interrupt,
+            CTRL_D => Key::EofOrDelete,
+            CTRL_E => Key::End,
+            CTRL_F => Key::Right,
+            CTRL_K => Key::KillToEnd,
+            CTRL_N => Key::Down,
+            CTRL_P => Key::Up,
+            CTRL_U => Key::KillToStart,
+            ESC => LineEditor::read_escape(bytes)?,
+            0x00..=0x1f => Key::Ignored,
+            _ => LineEditor::read_utf8_char(byte, bytes)?,
+        })
+    }
+
+    fn refresh(prompt: &str, line: &[char], cursor: usize) {
+        let text: String = line.iter().collect();
+        let mut out = format!("\r{}{}\x1b[K", prompt, text);
+        if cursor < line.len() {
+            out.push_str(&format!("\x1b[{}D", line.len() - cursor));
+        }
+        print!("{}", out);
+        let _ = io::stdout().flush();
+    }
+
+    fn word_left(line: &[char], cursor: usize) -> usize {
+        let mut cursor = cursor;
+        while cursor > 0 && !line[cursor - 1].is_alphanumeric() {
+            cursor -= 1;
+        }
+        while cursor > 0 && line[cursor - 1].is_alphanumeric() {
+            cursor -= 1;
+        }
+        cursor
+    }
+
+    fn word_right(line: &[char], cursor: usize) -> usize {
+        let mut cursor = cursor;
+        while cursor < line.len() && !line[cursor].is_alphanumeric() {
+            cursor += 1;
+        }
+        while cursor < line.len() && line[cursor].is_alphanumeric() {
+            cursor += 1;
+        }
+        cursor
+    }
+
+    fn edit_line(&mut self, prompt: &str) -> ReadResult {
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        // index into history while browsing it; history.len() is the new line
+        let mut history_index = self.history.len();
+        let mut pending_line: Vec<char> = Vec::new();
+        let mut bytes = std::iter::from_fn(LineEditor::read_byte);
+        LineEditor::refresh(prompt, &line, cursor);
+        loop {
+            let key = match LineEditor::read_key(&mut bytes) {
+                Some(key) => key,
+                None => {
+                    println!();
+                    return ReadResult::Eof;
+                }
+            };
+            match key {
+                Key::Enter => {
+                    println!();
+                    return ReadResult::Line(line.into_iter().collect());
+                }
+                Key::Interrupt => {
+                    println!("^C");
+                    return ReadResult::Interrupted;
+                }
+                Key::EofOrDelete if line.is_empty() => {
+                    println!();
+                    return ReadResult::Eof;
+                }
+                Key::EofOrDelete | Key::Delete => {
+                    if cursor < line.len() {
+                        line.remove(cursor);
+                    }
+                }
+                Key::Char(ch) => {
+                    line.insert(cursor, ch);
+                    cursor += 1;
+                }
+                Key::Backspace => {
+                    if cursor > 0 {
+                        cursor -= 1;
+                        line.remove(cursor);
+                    }
+                }
+                Key::Left => cursor = cursor.saturating_sub(1),
+                Key::Right => cursor = (cursor + 1).min(line.len()),
+                Key::WordLeft => cursor = LineEditor::word_left(&line, cursor),
+                Key::WordRight => cursor = LineEditor::word_right(&line, cursor),
+                Key::Home => cursor = 0,
+                Key::End => cursor = line.len(),
+                Key::KillToEnd => line.truncate(cursor),
+                Key::KillToStart => {
+                    line.drain(..cursor);
+                    cursor = 0;
+                }
+                Key::Up | Key::Down => {
+                    let next_index = match key {
+                        Key::Up if history_index > 0 => history_index - 1,
+                        Key::Down if history_index < self.history.len() => history_index + 1,
+                        _ => continue
+                    };
+                    if history_index == self.history.len() {
+                        pending_line = line.clone();
+                    }
+                    history_index = next_index;
+                    line = match self.history.get(history_index) {
+                        Some(entry) => entry.chars().collect(),
+                        None => pending_line.clone()
+                    };
+                    cursor = line.len();
+                }
+                Key::Ignored => continue
+            }
+            LineEditor::refresh(prompt, &line, cursor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(input: &[u8]) -> Vec<Key> {
+        let mut bytes = input.iter().cloned();
+        std::iter::from_fn(|| LineEditor::read_key(&mut bytes)).collect()
+    }
+
+    #[test]
+    fn csi_sequences() {
+        assert_eq!(keys(b"\x1b[A\x1b[1B\x1b[C\x1bOD\x1b[H\x1bOF"),
+                   [Key::Up, Key::Down, Key::Right, Key::Left, Key::Home, Key::End]);
+        assert_eq!(keys(b"\x1b[1;5D\x1b[1;5C\x1b[1;3D\x1bf\x1bb"),
+                   [Key::WordLeft, Key::WordRight, Key::WordLeft, Key::WordRight, Key::WordLeft]);
+        assert_eq!(keys(b"\x1b[3~\x1b[1~\x1b[8~"), [Key::Delete, Key::Home, Key::End]);
+        // unknown sequences are consumed up to their final byte
+        assert_eq!(keys(b"\x1b[200~x\x1b[1;2Ay"),
+                   [Key::Ignored, Key::Char('x'), Key::Ignored, Key::Char('y')]);
+        assert_eq!(keys(b"\x1b["), []);
+    }
+
+    #[test]
+    fn utf8_input() {
+        assert_eq!(keys("a中😀".as_bytes()), [Key::Char('a'), Key::Char('中'), Key::Char('😀')]);
+        assert_eq!(keys(b"\x80a\xff"), [Key::Ignored, Key::Char('a'), Key::Ignored]);
+        assert_eq!(keys(b"\xe4\x41\x41b"), [Key::Ignored, Key::Char('b')]);
+        assert_eq!(keys(b"\xe4\xb8"), []);
+    }
+
+    #[test]
+    fn word_movement() {
+        let line: Vec<char> = "local x = foo.bar".chars().collect();
+        assert_eq!(LineEditor::word_left(&line, line.len()), 14);
+        assert_eq!(LineEditor::word_left(&line, 14), 10);
+        assert_eq!(LineEditor::word_left(&line, 7), 6);
+        assert_eq!(LineEditor::word_left(&line, 3), 0);
+        assert_eq!(LineEditor::word_left(&line, 0), 0);
+        assert_eq!(LineEditor::word_right(&line, 0), 5);
+        assert_eq!(LineEditor::word_right(&line, 5), 7);
+        assert_eq!(LineEditor::word_right(&line, 7), 13);
+        assert_eq!(LineEditor::word_right(&line, 13), line.len());
+        assert_eq!(LineEditor::word_right(&line, line.len()), line.len());
+    }
+}
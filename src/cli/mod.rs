@@ -0,0 +1,268 @@
+mod line_edit;
+mod repl;
+mod subcommand;
+
+use rust_luac::ast::ast_def::stmt_def::block_def::Block;
+use rust_luac::ast::parser::Parser;
+use std::fs::File;
+use std::io::{self, Read, IsTerminal};
+
+const PROG_NAME: &str = "lua";
+const USAGE: &str = "usage: lua [options] [script [args]]
+Available options are:
+  -e stat  execute string 'stat'
+  -i       enter interactive mode after executing 'script'
+  -l name  require library 'name'
+  -v       show version information
+  -E       ignore environment variables
+  --       stop handling options
+  -        stop handling options and execute stdin
+  --stage name [args]  run a single compiler stage: tokens, ast, check,
+                       compile, disasm or run ('--stage help' for details)
+A script whose name starts with '-' is run with 'lua -- script'.";
+
+/// A `-e` or `-l` option, kept in command line order.
+pub enum PreRun {
+    Exec(String),
+    Require(String),
+}
+
+/// Command line of the `lua` stand-alone interpreter.
+pub struct Options {
+    pub interactive: bool,
+    pub show_version: bool,
+    pub has_exec: bool,
+    pub ignore_env: bool,
+    pub pre_runs: Vec<PreRun>,
+    /// Script path (`-` for stdin) followed by its arguments.
+    pub script: Option<(String, Vec<String>)>,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            interactive: false,
+            show_version: false,
+            has_exec: false,
+            ignore_env: false,
+            pre_runs: Vec::new(),
+            script: None,
+        };
+        let mut index = 1;
+        while index < args.len() {
+            let arg = &args[index];
+            if !arg.starts_with('-') || arg == "-" {
+                options.script = Some((arg.clone(), args[index + 1..].to_vec()));
+                break;
+            }
+            match arg.as_str() {
+                "--" => {
+                    if index + 1 < args.len() {
+                        options.script = Some((args[index + 1].clone(), args[index + 2..].to_vec()));
+                    }
+                    break;
+                }
+                "-E" => options.ignore_env = true,
+                "-i" => {
+                    options.interactive = true;
+                    options.show_version = true;
+                }
+                "-v" => options.show_version = true,
+                _ if arg.starts_with("-e") || arg.starts_with("-l") => {
+                    let opt = &arg[..2];
+                    let value = if arg.len() > 2 {
+                        arg[2..].to_string()
+                    } else {
+                        index += 1;
+                        match args.get(index) {
+                            Some(value) if !value.starts_with('-') => value.clone(),
+                            _ => return Err(format!("'{}' needs argument", opt))
+                        }
+                    };
+                    if opt == "-e" {
+                        options.has_exec = true;
+                        options.pre_runs.push(PreRun::Exec(value));
+                    } else {
+                        options.pre_runs.push(PreRun::Require(value));
+                    }
+                }
+                _ => return Err(format!("unrecognized option '{}'", arg))
+            }
+            index += 1;
+        }
+        Ok(options)
+    }
+}
+
+/// Compiles a chunk, returning a syntax error as its message.
+pub fn load_chunk(code: &str, chunk_name: &str) -> Result<Block, String> {
+    Parser::new(code, chunk_name).parse_chunk().map_err(|err| err.to_string())
+}
+
+fn print_version() {
+    println!("Lua 5.3 (RustLuac {})", env!("CARGO_PKG_VERSION"));
+}
+
+fn report(msg: &str) {
+    eprintln!("{}: {}", PROG_NAME, msg);
+}
+
+fn read_script(path: &str) -> Result<(String, String), String> {
+    let mut code = String::new();
+    let chunk_name = if path == "-" {
+        io::stdin().read_to_string(&mut code)
+            .map_err(|err| format!("cannot read stdin: {}", err))?;
+        "stdin".to_string()
+    } else {
+        File::open(path).and_then(|mut file| file.read_to_string(&mut code))
+            .map_err(|err| format!("cannot open {}: {}", path, err))?;
+        path.to_string()
+    };
+    // skip a leading '#!' line but keep its newline so line numbers stay right
+    if code.starts_with('#') {
+        let first_line_len = code.find(['\n', '\r']).unwrap_or(code.len());
+        code.replace_range(..first_line_len, "");
+    }
+    Ok((code, chunk_name))
+}
+
+fn run_chunk(code: &str, chunk_name: &str) -> Result<(), String> {
+    load_chunk(code, chunk_name)?;
+    Err(format!("{}: cannot run chunk: this build has no interpreter yet", chunk_name))
+}
+
+fn handle_lua_init() -> Result<(), String> {
+    let (name, init) = match std::env::var("LUA_INIT_5_3") {
+        Ok(init) => ("LUA_INIT_5_3", init),
+        Err(_) => match std::env::var("LUA_INIT") {
+            Ok(init) => ("LUA_INIT", init),
+            Err(_) => return Ok(())
+        }
+    };
+    match init.strip_prefix('@') {
+        Some(path) => {
+            let (code, chunk_name) = read_script(path)?;
+            run_chunk(&code, &chunk_name)
+        }
+        None => run_chunk(&init, name)
+    }
+}
+
+fn handle_pre_run(pre_run: &PreRun) -> Result<(), String> {
+    match pre_run {
+        PreRun::Exec(stat) => run_chunk(stat, "(command line)"),
+        PreRun::Require(name) => {
+            Err(format!("module '{}' not found: require is not supported yet", name))
+        }
+    }
+}
+
+fn handle_script(path: &str) -> Result<(), String> {
+    let (code, chunk_name) = read_script(path)?;
+    run_chunk(&code, &chunk_name)
+}
+
+fn run_options(options: &Options) -> Result<(), String> {
+    if options.show_version {
+        print_version();
+    }
+    if !options.ignore_env {
+        handle_lua_init()?;
+    }
+    for pre_run in options.pre_runs.iter() {
+        handle_pre_run(pre_run)?;
+    }
+    if let Some((path, _)) = &options.script {
+        handle_script(path)?;
+    }
+    if options.interactive {
+        repl::do_repl();
+    } else if options.script.is_none() && !options.has_exec && !options.show_version {
+        if io::stdin().is_terminal() {
+            print_version();
+            repl::do_repl();
+        } else {
+            handle_script("-")?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs the stand-alone interpreter, or with `--stage` one of the compiler
+/// stage subcommands, and returns its exit status.
+pub fn run(args: &[String]) -> i32 {
+    if args.get(1).map(|arg| arg.as_str()) == Some("--stage") {
+        return subcommand::run_command(args.get(2).map_or("", |arg| arg.as_str()), args.get(3..).unwrap_or(&[]));
+    }
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(msg) => {
+            report(&msg);
+            eprintln!("{}", USAGE);
+            return 1;
+        }
+    };
+    match run_options(&options) {
+        Ok(()) => 0,
+        Err(msg) => {
+            report(&msg);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        let args: Vec<String> = std::iter::once("lua").chain(args.iter().cloned())
+            .map(|arg| arg.to_string()).collect();
+        Options::parse(&args)
+    }
+
+    fn pre_runs(options: &Options) -> Vec<String> {
+        options.pre_runs.iter().map(|pre_run| match pre_run {
+            PreRun::Exec(stat) => format!("e {}", stat),
+            PreRun::Require(name) => format!("l {}", name),
+        }).collect()
+    }
+
+    #[test]
+    fn exec_and_require_values() {
+        let options = parse(&["-ex=1", "-e", "y=2", "-lmod", "-l", "other"]).unwrap();
+        assert!(options.has_exec);
+        assert_eq!(pre_runs(&options), ["e x=1", "e y=2", "l mod", "l other"]);
+        assert!(options.script.is_none());
+        assert!(!parse(&["-lmod"]).unwrap().has_exec);
+    }
+
+    #[test]
+    fn script_and_arguments() {
+        let options = parse(&["-v", "script.lua", "-e", "x"]).unwrap();
+        assert!(options.show_version);
+        assert_eq!(options.script, Some(("script.lua".to_string(), vec!["-e".to_string(), "x".to_string()])));
+        assert_eq!(parse(&["-", "a"]).unwrap().script, Some(("-".to_string(), vec!["a".to_string()])));
+        assert_eq!(parse(&["--", "-script", "a"]).unwrap().script,
+                   Some(("-script".to_string(), vec!["a".to_string()])));
+        assert_eq!(parse(&["-E", "--"]).unwrap().script, None);
+        assert!(parse(&["-E"]).unwrap().ignore_env);
+    }
+
+    #[test]
+    fn interactive_shows_version() {
+        let options = parse(&["-i"]).unwrap();
+        assert!(options.interactive);
+        assert!(options.show_version);
+        assert!(!parse(&["-v"]).unwrap().interactive);
+    }
+
+    #[test]
+    fn option_errors() {
+        assert_eq!(parse(&["-e"]).err().unwrap(), "'-e' needs argument");
+        assert_eq!(parse(&["-l", "-i"]).err().unwrap(), "'-l' needs argument");
+        assert_eq!(parse(&["-x"]).err().unwrap(), "unrecognized option '-x'");
+        assert_eq!(parse(&["-iv"]).err().unwrap(), "unrecognized option '-iv'");
+        assert_eq!(parse(&["--stage"]).err().unwrap(), "unrecognized option '--stage'");
+    }
+}
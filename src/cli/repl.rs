@@ -0,0 +1,119 @@
+use super::line_edit::{LineEditor, ReadResult};
+use super::{load_chunk, report, run_chunk};
+use std::fs;
+use std::path::PathBuf;
+
+const PROMPT: &str = "> ";
+const PROMPT2: &str = ">> ";
+const CHUNK_NAME: &str = "stdin";
+const HISTORY_FILE: &str = ".rust_luac_history";
+const MAX_HISTORY: usize = 1000;
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+fn load_history() -> Vec<String> {
+    let content = history_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    let history: Vec<String> = content.lines().map(|line| line.to_string()).collect();
+    let skip = history.len().saturating_sub(MAX_HISTORY);
+    history.into_iter().skip(skip).collect()
+}
+
+fn save_history(editor: &LineEditor) {
+    if !editor.is_interactive() {
+        return;
+    }
+    if let Some(path) = history_path() {
+        let history = editor.history();
+        let skip = history.len().saturating_sub(MAX_HISTORY);
+        let mut content = history[skip..].join("\n");
+        content.push('\n');
+        let _ = fs::write(path, content);
+    }
+}
+
+/// The parser reports a chunk that ends too early with a message ending in
+/// `<eof>`, in which case more input may complete it.
+fn is_incomplete(msg: &str) -> bool {
+    msg.ends_with("<eof>")
+}
+
+fn read_statement(editor: &mut LineEditor) -> Option<String> {
+    let first_line = loop {
+        match editor.read_line(PROMPT) {
+            ReadResult::Line(line) => break line,
+            ReadResult::Interrupted => continue,
+            ReadResult::Eof => return None,
+        }
+    };
+    if first_line.trim().is_empty() {
+        return Some(String::new());
+    }
+    editor.add_history(&first_line);
+    // '=exp' is a shorthand for 'return exp'
+    let first_line = match first_line.strip_prefix('=') {
+        Some(exp) => format!("return {}", exp),
+        None => first_line,
+    };
+    // try the line as an expression first so its value can be shown
+    let ret_code = format!("return {};", first_line);
+    if load_chunk(&ret_code, CHUNK_NAME).is_ok() {
+        return Some(ret_code);
+    }
+
+    let mut code = first_line;
+    loop {
+        match load_chunk(&code, CHUNK_NAME) {
+            Err(ref msg) if is_incomplete(msg) => {}
+            _ => return Some(code),
+        }
+        match editor.read_line(PROMPT2) {
+            ReadResult::Line(line) => {
+                editor.add_history(&line);
+                code.push('\n');
+                code.push_str(&line);
+            }
+            // abandon the unfinished statement and start over
+            ReadResult::Interrupted => return Some(String::new()),
+            ReadResult::Eof => return None,
+        }
+    }
+}
+
+pub fn do_repl() {
+    let mut editor = LineEditor::new(load_history());
+    while let Some(code) = read_statement(&mut editor) {
+        if code.is_empty() {
+            continue;
+        }
+        if let Err(msg) = run_chunk(&code, CHUNK_NAME) {
+            report(&msg);
+        }
+        save_history(&editor);
+    }
+    save_history(&editor);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn incomplete(code: &str) -> bool {
+        is_incomplete(&load_chunk(code, CHUNK_NAME).unwrap_err())
+    }
+
+    #[test]
+    fn unfinished_chunks_are_incomplete() {
+        assert!(incomplete("if x then"));
+        assert!(incomplete("f("));
+        assert!(incomplete("x = {1,"));
+        assert!(incomplete("x = [[long"));
+        assert!(incomplete("--[[ comment"));
+        assert!(!incomplete("x = = 1"));
+        assert!(!incomplete("x = 'abc"));
+        assert!(!incomplete("end"));
+    }
+}
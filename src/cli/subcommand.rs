@@ -0,0 +1,99 @@
+use super::{load_chunk, read_script, report, run_chunk};
+use rust_luac::ast::lexer::Lexer;
+use std::io::{self, Write};
+
+const USAGE: &str = "usage: lua --stage <name> [options] file
+Stages of the compiler that can be run on their own:
+  tokens file               print the token stream with line and column
+  ast file                  print the parsed syntax tree
+  check file                parse the file, reporting only the exit status
+  compile [-o out] file     write a precompiled chunk (default luac.out)
+  disasm file               list the bytecode of a file
+  run file [args]           run a script, like 'lua file [args]'
+A file of '-' reads stdin.";
+
+/// Output that can no longer be written because its reader went away, as
+/// with `| head`, ends the stage quietly.
+fn output_result(result: io::Result<()>) -> Result<(), String> {
+    match result {
+        Err(ref err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.map_err(|err| format!("cannot write output: {}", err))
+    }
+}
+
+fn dump_tokens(path: &str) -> Result<(), String> {
+    let (code, chunk_name) = read_script(path)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for token in Lexer::new(&code, &chunk_name) {
+        let token = token.map_err(|err| err.to_string())?;
+        if let Err(err) = writeln!(out, "{}", token) {
+            return output_result(Err(err));
+        }
+    }
+    output_result(out.flush())
+}
+
+fn dump_ast(path: &str) -> Result<(), String> {
+    let (code, chunk_name) = read_script(path)?;
+    let block = load_chunk(&code, &chunk_name)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    output_result(writeln!(out, "{:#?}", block).and_then(|_| out.flush()))
+}
+
+fn check(path: &str) -> Result<(), String> {
+    let (code, chunk_name) = read_script(path)?;
+    load_chunk(&code, &chunk_name).map(|_| ())
+}
+
+fn compile(args: &[String]) -> Result<(), String> {
+    let (output, path) = match args {
+        [opt, output, path] if opt == "-o" => (output.as_str(), path),
+        [path] => ("luac.out", path),
+        _ => return Err(format!("bad arguments to 'compile'\n{}", USAGE))
+    };
+    let (code, chunk_name) = read_script(path)?;
+    load_chunk(&code, &chunk_name)?;
+    Err(format!("cannot write {}: this build has no code generator yet", output))
+}
+
+fn disasm(path: &str) -> Result<(), String> {
+    let (code, chunk_name) = read_script(path)?;
+    load_chunk(&code, &chunk_name)?;
+    Err(format!("cannot list {}: this build has no code generator yet", chunk_name))
+}
+
+fn run_script(path: &str) -> Result<(), String> {
+    let (code, chunk_name) = read_script(path)?;
+    run_chunk(&code, &chunk_name)
+}
+
+/// Runs the stage `command` with the arguments that follow it and returns
+/// the exit status.
+pub fn run_command(command: &str, args: &[String]) -> i32 {
+    let result = match (command, args) {
+        ("help", _) => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        ("compile", _) => compile(args),
+        ("run", [path, ..]) => run_script(path),
+        ("tokens", [path]) => dump_tokens(path),
+        ("ast", [path]) => dump_ast(path),
+        ("check", [path]) => check(path),
+        ("disasm", [path]) => disasm(path),
+        ("tokens", _) | ("ast", _) | ("check", _) | ("disasm", _) | ("run", _) => {
+            Err(format!("bad arguments to '{}'\n{}", command, USAGE))
+        }
+        ("", _) => Err(format!("'--stage' needs argument\n{}", USAGE)),
+        _ => Err(format!("unknown stage '{}'\n{}", command, USAGE))
+    };
+    match result {
+        Ok(()) => 0,
+        Err(msg) => {
+            report(&msg);
+            1
+        }
+    }
+}
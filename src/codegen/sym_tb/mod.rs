@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 mod sym_tb;
 mod scope;
 mod sym;
\ No newline at end of file
@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use crate::ast::lexer::token::Token;
-use std::borrow::Borrow;
 
+#[allow(dead_code)]
 struct SymbolTable {
     scope_index: HashMap<Token, Vec<usize>>,
     token_stack: Vec<HashSet<Token>>,
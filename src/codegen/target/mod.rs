@@ -0,0 +1 @@
+mod target_gen;
\ No newline at end of file
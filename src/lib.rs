@@ -0,0 +1,3 @@
+pub mod ast;
+mod util;
+mod codegen;